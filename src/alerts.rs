@@ -0,0 +1,299 @@
+//! Price alerts
+//!
+//! Rules are written one per line, e.g.
+//!
+//! ```text
+//! BTCUSDT above 70000
+//! ETH below 2000
+//! ETH moves 3% in 5m
+//! any 24h +20%
+//! ```
+//!
+//! A symbol without quote currency implies `USDT`, `any` matches all symbols.
+//! `moves` and `24h` percentages without sign trigger in both directions.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::ui::Msg;
+use std::collections::{HashMap, VecDeque};
+use std::process::Stdio;
+use tokio::sync::mpsc::UnboundedSender;
+use dec::Decimal64;
+
+/// Number of triggered alerts kept in the log
+const ALERT_LOG_LEN: usize = 200;
+
+/// Default minimum time between two runs of the alert command
+pub const ALERT_COMMAND_INTERVAL_MILLIS: u64 = 10_000;
+
+/// A percentage threshold. Signed thresholds only trigger in their direction.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Percent {
+    Up(f64),        // e.g. +3%
+    Down(f64),      // e.g. -3%
+    Either(f64),    // e.g. 3%
+}
+
+impl Percent {
+    /// Is the threshold crossed by `value` (in percent)? Never for NAN.
+    pub fn is_crossed(self: &Self, value: f64) -> bool {
+        match self {
+            _ if value.is_nan() => false,
+            Percent::Up(p) => value >= *p,
+            Percent::Down(p) => value <= -*p,
+            Percent::Either(p) => value.abs() >= *p,
+        }
+    }
+}
+
+/// The condition of an alert `Rule`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Above(Decimal64),       // price above
+    Below(Decimal64),       // price below
+    Moves(Percent, u32),    // percent move within minutes
+    Change24h(Percent),     // percent change in 24h
+}
+
+/// An alert rule
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub text: String,           // the rule as written by the user
+    symbol: Option<Symbol>,     // `None` for any symbol
+    condition: Condition,
+}
+
+/// Parse a percentage like `3%`, `+20%` or `-5%`
+fn parse_percent(s: &str) -> Result<Percent, String> {
+    let p = s.strip_suffix('%').ok_or(format!("Expected percentage, got '{}'", s))?;
+    let v: f64 = p.parse().map_err(|_| format!("Bad percentage '{}'", s))?;
+    if      p.starts_with('+') { Ok(Percent::Up(v)) }
+    else if p.starts_with('-') { Ok(Percent::Down(-v)) }
+    else                       { Ok(Percent::Either(v)) }
+}
+
+/// Parse a window like `5m`, `5min`, `1h` (or `5` followed by `min` as separate word)
+fn parse_minutes(s: &str) -> Result<u32, String> {
+    let s = s.to_lowercase();
+    let (n, factor) = if let Some(n) = s.strip_suffix("min") { (n, 1) }
+                      else if let Some(n) = s.strip_suffix('m') { (n, 1) }
+                      else if let Some(n) = s.strip_suffix('h') { (n, 60) }
+                      else { (s.as_str(), 1) };
+    n.parse::<u32>().map(|n| n*factor).map_err(|_| format!("Bad time window '{}'", s))
+}
+
+impl Rule {
+    /// Parse a rule, see module documentation for the syntax
+    pub fn parse(text: &str) -> Result<Rule, String> {
+        let words: Vec<&str> = text.split_whitespace().filter(|w| !w.eq_ignore_ascii_case("symbol")).collect();
+        if words.len() < 3 { return Err(format!("Incomplete rule '{}'", text)); }
+        let symbol = if words[0].eq_ignore_ascii_case("any") { None }
+                     else { Some(parse_symbol(&words[0].to_uppercase().replace("/", ""))?) };
+        let rest: Vec<String> = words[1..].iter().map(|w| w.to_lowercase()).collect();
+        let condition = match rest[0].as_str() {
+            "above" | ">" => Condition::Above(rest[1].parse().map_err(|_| format!("Bad price '{}'", rest[1]))?),
+            "below" | "<" => Condition::Below(rest[1].parse().map_err(|_| format!("Bad price '{}'", rest[1]))?),
+            "moves" => {
+                // moves <pct>% in <n>[m|min|h]  or  moves <pct>% in <n> min
+                if rest.len() < 4 || rest[2] != "in" { return Err(format!("Expected 'moves X% in Nm', got '{}'", text)); }
+                let p = parse_percent(&rest[1])?;
+                let window = match rest.get(4) { Some(unit) => format!("{}{}", rest[3], unit), None => rest[3].clone() };
+                Condition::Moves(p, parse_minutes(&window)?)
+            },
+            _ => {
+                // 24h <pct>%  or  <pct>% 24h
+                let p = if rest[0] == "24h" { &rest[1] } else if rest[1] == "24h" { &rest[0] }
+                        else { return Err(format!("Unknown rule '{}'", text)); };
+                Condition::Change24h(parse_percent(p)?)
+            }
+        };
+        if let Condition::Moves(_, 0) = condition { return Err(String::from("Time window must be positive")); }
+        Ok(Rule { text: String::from(text.trim()), symbol: symbol, condition: condition })
+    }
+    /// Does the rule apply to `symbol`? A rule symbol without quote implies USDT.
+    pub fn matches(self: &Self, symbol: &Symbol) -> bool {
+        match &self.symbol {
+            None => true,
+            Some(s) => {
+                if s == symbol { return true; }
+                let mut usdt = s.clone();
+                if usdt.push_str("USDT").is_err() { return false; }
+                &usdt == symbol
+            }
+        }
+    }
+    /// Is the condition met?
+    ///
+    /// `px` is the current price, `change_24h` the 24h change in percent and `change` a function
+    /// that returns the change in percent over the given number of minutes.
+    pub fn is_met(self: &Self, px: Decimal64, change_24h: f64, change: &dyn Fn(u32) -> f64) -> bool {
+        match &self.condition {
+            Condition::Above(threshold) => px > *threshold,
+            Condition::Below(threshold) => px < *threshold,
+            Condition::Moves(p, minutes) => p.is_crossed(change(*minutes)),
+            Condition::Change24h(p) => p.is_crossed(change_24h),
+        }
+    }
+}
+
+/// A triggered alert
+#[derive(Debug, Clone)]
+pub struct Triggered {
+    pub ts: u64,            // timestamp (millis)
+    pub symbol: Symbol,
+    pub px: Decimal64,
+    pub rule: String,       // text of the rule
+}
+
+/// The alert rules and the log of triggered alerts.
+///
+/// Alerts are edge triggered: a rule fires once when its condition becomes true for a symbol and
+/// is re-armed when the condition is false again.
+pub struct Alerts {
+    pub rules: Vec<Rule>,
+    pub log: VecDeque<Triggered>,           // most recent first
+    active: HashMap<(usize, Symbol), bool>, // (rule index, symbol) -> condition was met at last check
+}
+
+impl Alerts {
+    /// New `Alerts` without rules
+    pub fn new() -> Self {
+        Alerts { rules: Vec::new(), log: VecDeque::with_capacity(ALERT_LOG_LEN), active: HashMap::new() }
+    }
+    /// Add a rule
+    pub fn add(self: &mut Self, rule: Rule) {
+        self.rules.push(rule);
+    }
+    /// Remove the rule with index `i`
+    pub fn remove(self: &mut Self, i: usize) -> Option<Rule> {
+        if i >= self.rules.len() { return None; }
+        // keep the state of the other rules, their indices above `i` shift down by one
+        self.active = self.active.drain().filter(|((j, _), _)| *j != i)
+            .map(|((j, symbol), met)| ((if j > i { j-1 } else { j }, symbol), met)).collect();
+        Some(self.rules.remove(i))
+    }
+    /// Check all rules for `symbol`, log and return the ones that fired
+    pub fn check(self: &mut Self, ts: u64, symbol: &Symbol, px: Decimal64, change_24h: f64,
+                 change: &dyn Fn(u32) -> f64) -> Vec<Triggered> {
+        let mut out = Vec::new();
+        for (i, rule) in self.rules.iter().enumerate() {
+            if !rule.matches(symbol) { continue; }
+            let met = rule.is_met(px, change_24h, change);
+            let was_met = self.active.insert((i, symbol.clone()), met).unwrap_or(false);
+            if met && !was_met {
+                out.push(Triggered { ts: ts, symbol: symbol.clone(), px: px, rule: rule.text.clone() });
+            }
+        }
+        for t in out.iter() {
+            if self.log.len() >= ALERT_LOG_LEN { self.log.pop_back(); }
+            self.log.push_front(t.clone());
+        }
+        out
+    }
+}
+
+/// Shell command that is run when an alert is triggered.
+///
+/// The command is run with `sh -c` and gets the alert in the environment variables
+/// `COINLIVE_SYMBOL`, `COINLIVE_PRICE`, `COINLIVE_RULE` and `COINLIVE_TS` (millis).
+/// Runs are rate limited to one per `min_interval` millis, failures are reported to the `UI`.
+pub struct Hook {
+    command: String,
+    min_interval: u64,  // millis
+    last_run: u64,      // ts of last run (millis)
+}
+
+impl Hook {
+    /// New `Hook` running `command` at most every `min_interval` millis
+    pub fn new(command: String, min_interval: u64) -> Self {
+        Hook { command: command, min_interval: min_interval, last_run: 0 }
+    }
+    /// Run the command for the triggered alert `t` unless rate limited.
+    ///
+    /// Returns `false` if the run was skipped because of the rate limit.
+    pub fn run(self: &mut Self, t: &Triggered, now: u64, ui_tx: &UnboundedSender<Msg>) -> bool {
+        if self.last_run != 0 && now < self.last_run + self.min_interval { return false; }
+        self.last_run = now;
+        let child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("COINLIVE_SYMBOL", t.symbol.to_string())
+            .env("COINLIVE_PRICE", fmt_dec(t.px))
+            .env("COINLIVE_RULE", &t.rule)
+            .env("COINLIVE_TS", t.ts.to_string())
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        let ui_tx = ui_tx.clone();
+        match child {
+            Ok(child) => {
+                tokio::spawn(async move {
+                    let msg = match child.wait_with_output().await {
+                        Ok(output) if output.status.success() => return,
+                        Ok(output) => {
+                            let stderr = String::from_utf8_lossy(&output.stderr);
+                            format!("Alert command failed ({}): {}", output.status, stderr.lines().next().unwrap_or(""))
+                        },
+                        Err(e) => format!("Alert command failed: {}", e)
+                    };
+                    ui_tx.send(Msg::Msg(msg)).unwrap_or(());
+                });
+            },
+            Err(e) => {
+                ui_tx.send(Msg::Msg(format!("Failed to run alert command: {}", e))).unwrap_or(());
+            }
+        }
+        true
+    }
+}
+
+#[test]
+fn test_parse_rule() -> Result<(), Box<dyn std::error::Error>> {
+    assert_eq!(Rule::parse("BTCUSDT above 70000")?.condition, Condition::Above("70000".parse()?));
+    assert_eq!(Rule::parse("ETH moves 3% in 5 min")?.condition, Condition::Moves(Percent::Either(3.0), 5));
+    assert_eq!(Rule::parse("ETH moves 3% in 1h")?.condition, Condition::Moves(Percent::Either(3.0), 60));
+    assert_eq!(Rule::parse("any symbol +20% 24h")?.condition, Condition::Change24h(Percent::Up(20.0)));
+    assert_eq!(Rule::parse("ETH 24h -5%")?.condition, Condition::Change24h(Percent::Down(5.0)));
+    assert!(Rule::parse("ETH sideways").is_err());
+    assert!(Rule::parse("ETH moves 3% in 0m").is_err());
+    assert!(Rule::parse(&format!("{} above 1", "X".repeat(31))).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_edge_triggered() -> Result<(), Box<dyn std::error::Error>> {
+    let mut alerts = Alerts::new();
+    alerts.add(Rule::parse("BTC above 70000")?);
+    alerts.add(Rule::parse("any +20% 24h")?);
+    let btc = mock_symbol("BTCUSDT");
+    let no_change = |_: u32| f64::NAN;
+    assert_eq!(alerts.check(1, &btc, "69000".parse()?, 0.0, &no_change).len(), 0);
+    assert_eq!(alerts.check(2, &btc, "70001".parse()?, 0.0, &no_change).len(), 1);
+    assert_eq!(alerts.check(3, &btc, "70002".parse()?, 21.0, &no_change).len(), 1);
+    assert_eq!(alerts.check(4, &btc, "70003".parse()?, -21.0, &no_change).len(), 0);
+    assert_eq!(alerts.check(5, &btc, "69000".parse()?, 0.0, &no_change).len(), 0);
+    assert_eq!(alerts.check(6, &btc, "70001".parse()?, 0.0, &no_change).len(), 1);
+    assert_eq!(alerts.log.len(), 3);
+    // removing a rule keeps the others armed or disarmed
+    alerts.add(Rule::parse("BTC below 80000")?);
+    assert_eq!(alerts.check(7, &btc, "70001".parse()?, 0.0, &no_change).len(), 1);
+    alerts.remove(0);
+    assert_eq!(alerts.check(8, &btc, "70001".parse()?, 0.0, &no_change).len(), 0);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hook() -> Result<(), Box<dyn std::error::Error>> {
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut hook = Hook::new(String::from("echo \"$COINLIVE_SYMBOL $COINLIVE_PRICE\" >&2; exit 3"), 1000);
+    let t = Triggered { ts: 1, symbol: mock_symbol("BTCUSDT"), px: "70001".parse()?, rule: String::from("BTC above 70000") };
+    assert!(hook.run(&t, 10_000, &tx));
+    assert!(!hook.run(&t, 10_500, &tx));    // rate limited
+    match rx.recv().await {
+        Some(Msg::Msg(msg)) => assert!(msg.ends_with("BTCUSDT 70001"), "{}", msg),
+        msg => panic!("Unexpected {:?}", msg)
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+//! Realized volatility and return correlations
+//!
+//! The close prices of several assets are aligned by the open time of the bars, only bars that
+//! exist for all assets are kept. From the last `window` bars the log returns are computed,
+//! their annualized standard deviation is the realized volatility, and their pairwise Pearson
+//! correlation gives the correlation matrix.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::ui::Msg;
+use crate::cache::{KlineCache, get_klines_cached};
+use std::collections::HashSet;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Seconds per year, used for annualizing the volatility
+const SECONDS_PER_YEAR: f64 = 365.0*24.0*60.0*60.0;
+
+/// Close prices of several assets, aligned by bar open time
+pub struct Series {
+    pub assets: Vec<Symbol>,
+    pub interval: Interval,
+    pub closes: Vec<Vec<f64>>,      // one vector per asset, all of the same length
+}
+
+/// Volatility and correlation matrix
+pub struct Matrix {
+    pub assets: Vec<Symbol>,
+    pub window: usize,              // number of bars used
+    pub vols: Vec<f64>,             // annualized realized volatility in percent
+    pub corr: Vec<Vec<f64>>,        // pairwise correlation of the returns
+}
+
+impl Series {
+    /// Align the `bars` of each asset, dropping bars that are missing for any asset
+    pub fn new(interval: Interval, bars: Vec<(Symbol, Vec<Bar>)>) -> Self {
+        let mut common: Option<HashSet<u64>> = None;
+        for (_, bs) in bars.iter() {
+            let ts: HashSet<u64> = bs.iter().map(|b| b.t).collect();
+            common = Some(match common { Some(c) => c.intersection(&ts).cloned().collect(), None => ts });
+        }
+        let common = common.unwrap_or_default();
+        let (assets, closes) = bars.into_iter().map(|(asset, bs)| {
+            (asset, bs.iter().filter(|b| common.contains(&b.t)).map(|b| b.c as f64).collect())
+        }).unzip();
+        Series { assets: assets, interval: interval, closes: closes }
+    }
+    /// Number of aligned bars
+    pub fn len(self: &Self) -> usize {
+        self.closes.first().map(|c| c.len()).unwrap_or(0)
+    }
+    /// Compute volatilities and correlations over the last `window` bars
+    pub fn matrix(self: &Self, window: usize) -> Matrix {
+        let window = window.min(self.len());
+        let returns: Vec<Vec<f64>> = self.closes.iter().map(|c| log_returns(&c[c.len()-window..])).collect();
+        let vols = returns.iter().map(|r| volatility(r, &self.interval)).collect();
+        let corr = returns.iter().map(|a| returns.iter().map(|b| correlation(a, b)).collect()).collect();
+        Matrix { assets: self.assets.clone(), window: window, vols: vols, corr: corr }
+    }
+}
+
+/// Log returns of consecutive prices
+pub fn log_returns(px: &[f64]) -> Vec<f64> {
+    px.windows(2).map(|w| (w[1]/w[0]).ln()).collect()
+}
+
+/// Mean and standard deviation, NAN if there are fewer than two values
+fn mean_std(x: &[f64]) -> (f64, f64) {
+    if x.len() < 2 { return (f64::NAN, f64::NAN); }
+    let n = x.len() as f64;
+    let mean = x.iter().sum::<f64>()/n;
+    let var = x.iter().map(|v| (v-mean)*(v-mean)).sum::<f64>()/(n-1.0);
+    (mean, var.sqrt())
+}
+
+/// Annualized volatility in percent of `returns` sampled at `interval`
+pub fn volatility(returns: &[f64], interval: &Interval) -> f64 {
+    let (_, std) = mean_std(returns);
+    std*(SECONDS_PER_YEAR/interval.seconds() as f64).sqrt()*100.0
+}
+
+/// Pearson correlation of `a` and `b`, NAN if undefined
+pub fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len().min(b.len());
+    let (a, b) = (&a[..n], &b[..n]);
+    let (ma, sa) = mean_std(a);
+    let (mb, sb) = mean_std(b);
+    let cov = a.iter().zip(b.iter()).map(|(x, y)| (x-ma)*(y-mb)).sum::<f64>()/(n as f64-1.0);
+    cov/(sa*sb)
+}
+
+/// Download the klines of `symbols`, pairs of asset and market symbol, and send them as
+/// `Msg::AnalyticsBars`. The progress is sent as `Msg::Msg`, failed downloads are left out.
+pub async fn download(cache: Option<KlineCache>, interval: Interval, symbols: Vec<(Symbol, Symbol)>,
+                      ui_tx: UnboundedSender<Msg>) -> Result<(), String> {
+    let ui_failed = |e| format!("UI failed: {:?}", e);
+    let mut bars = Vec::with_capacity(symbols.len());
+    let mut failed: usize = 0;
+    for (i, (asset, symbol)) in symbols.iter().enumerate() {
+        ui_tx.send(Msg::Msg(format!("Getting {} klines for {} ({}/{})", interval, symbol, i+1, symbols.len()))).map_err(ui_failed)?;
+        match get_klines_cached(cache.as_ref(), symbol, &interval).await {
+            Ok((klines, _)) => bars.push((asset.clone(), klines)),
+            Err(_) => failed += 1
+        }
+    }
+    ui_tx.send(Msg::AnalyticsBars(interval, bars, failed)).map_err(ui_failed)
+}
+
+#[test]
+fn test_matrix() {
+    let bar = |t: u64, c: f32| Bar { t: t, o: c, h: c, l: c, c: c, v: 0.0 };
+    let series = Series::new(Interval::I1d, vec![
+        (mock_symbol("BTC"), vec![bar(0, 100.0), bar(1, 110.0), bar(2, 99.0), bar(3, 108.9)]),
+        (mock_symbol("ETH"), vec![bar(1, 10.0), bar(2, 9.0), bar(3, 9.9), bar(4, 1.0)]),
+        (mock_symbol("XRP"), vec![bar(1, 1.0), bar(2, 1.1), bar(3, 0.99)]),
+    ]);
+    assert_eq!(series.len(), 3);
+    let m = series.matrix(10);
+    assert_eq!(m.window, 3);
+    assert!((m.corr[0][1]-1.0).abs() < 1e-6);
+    assert!((m.corr[0][2]+1.0).abs() < 1e-6);
+    assert!((m.corr[1][1]-1.0).abs() < 1e-6);
+    let r = log_returns(&[100.0, 90.0, 99.0]);
+    assert!((m.vols[0]-volatility(&r, &Interval::I1d)).abs() < 1e-3);
+    assert!(correlation(&[1.0], &[1.0]).is_nan());
+}
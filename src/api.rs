@@ -0,0 +1,90 @@
+//! Local HTTP/JSON API over the live market state
+//!
+//! * `/prices` price, 24h change and volume of all markets, by volume
+//! * `/prices/{symbol}` the same for a single market, `BTC` is short for `BTCUSDT`
+//! * `/klines/{symbol}?interval=1h` the last 1000 klines, through the kline cache
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::http::{Request, Response, Handler};
+use crate::live::SharedLive;
+use crate::cache::{KlineCache, get_klines_cached};
+use crate::export::KlineRow;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// JSON response
+fn json<T: Serialize>(value: &T) -> Response {
+    match serde_json::to_string(value) {
+        Ok(body) => Response::ok("application/json", body),
+        Err(e) => Response::error(500, &e.to_string())
+    }
+}
+
+/// `/prices` and `/prices/{symbol}`
+fn prices(live: &SharedLive, symbol: Option<&str>) -> Response {
+    let live = match live.lock() {
+        Ok(live) => live,
+        Err(_) => return Response::error(500, "Prices unavailable")
+    };
+    match symbol {
+        None => json(&live.rows()),
+        Some(symbol) => match live.info(symbol).and_then(|info| live.row(info)) {
+            Some(row) => json(&row),
+            None => Response::error(404, &format!("No price for {}", symbol))
+        }
+    }
+}
+
+/// `/klines/{symbol}?interval=`, blocks until the klines are downloaded
+fn klines(live: &SharedLive, cache: Option<&KlineCache>, symbol: &str, interval: &str) -> Response {
+    let interval: Interval = match interval.parse() {
+        Ok(interval) => interval,
+        Err(e) => return Response::error(400, &e)
+    };
+    let symbol = match live.lock().map(|live| live.info(symbol).map(|info| info.symbol.clone())) {
+        Ok(Some(symbol)) => symbol,
+        Ok(None) => return Response::error(404, &format!("Unknown symbol {}", symbol)),
+        Err(_) => return Response::error(500, "Symbols unavailable")
+    };
+    let result = tokio::runtime::Handle::current().block_on(get_klines_cached(cache, &symbol, &interval))
+        .map_err(|e| e.to_string());
+    match result {
+        Ok((bars, _)) => json(&bars.iter().map(KlineRow::new).collect::<Vec<KlineRow>>()),
+        Err(e) => Response::error(502, &format!("Failed to get klines: {}", e))
+    }
+}
+
+/// Handler of the API
+pub fn handler(live: SharedLive, cache: Option<KlineCache>) -> Handler {
+    Arc::new(move |request: &Request| {
+        let parts: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+        match parts.as_slice() {
+            ["prices"] => prices(&live, None),
+            ["prices", symbol] => prices(&live, Some(symbol)),
+            ["klines", symbol] => klines(&live, cache.as_ref(), symbol, request.param("interval").unwrap_or("1m")),
+            _ => Response::error(404, "Not found, try /prices, /prices/{symbol} or /klines/{symbol}?interval=1h")
+        }
+    })
+}
+
+#[test]
+fn test_api() -> Result<(), Box<dyn std::error::Error>> {
+    let live = crate::live::Live::shared();
+    if let Ok(mut live) = live.lock() {
+        live.set_infos(&[mock_info("BTC", "USDT", "20")]);
+        live.update(1000, &[mock_update("BTCUSDT", 900, "110", "110")]);
+    }
+    let api = handler(live, None);
+    let get = |path: &str| Request::parse(&format!("GET {} HTTP/1.1\r\n\r\n", path)).map(|r| api(&r));
+    let response = get("/prices/btc").ok_or("bad request")?;
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body, "{\"symbol\":\"BTCUSDT\",\"base\":\"BTC\",\"quote\":\"USDT\",\"price\":110.0,\"change_24h\":0.0,\"volume\":20.0}");
+    let response = get("/prices").ok_or("bad request")?;
+    assert!(response.body.starts_with("[{\"symbol\":\"BTCUSDT\""));
+    assert_eq!(get("/prices/ETH").map(|r| r.status), Some(404));
+    assert_eq!(get("/klines/BTCUSDT?interval=2m").map(|r| r.status), Some(400));
+    assert_eq!(get("/klines/ETH?interval=1h").map(|r| r.status), Some(404));
+    assert_eq!(get("/").map(|r| r.status), Some(404));
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Triangular arbitrage scanner
+//!
+//! Three markets that connect three assets `A`, `B` and `C` form a loop `A->B->C->A`. Converting
+//! one unit of `A` along the loop gives the product of the three conversion rates. If this product
+//! is above 1 the loop is (before fees) profitable. Selling the base of a market is done at the
+//! best bid, buying it at the best ask. If there is no book ticker for a market its last price is used.
+//! Each leg pays the taker fee.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::ui::MarketState;
+use std::collections::HashMap;
+
+/// Binance taker fee in percent
+pub const DEFAULT_FEE_PCT: f64 = 0.1;
+
+/// A conversion from one asset into another via a market
+#[derive(Debug, Clone)]
+struct Leg {
+    to: Symbol,
+    market: Symbol,
+    rate: f64,          // units of `to` per unit of the source asset
+}
+
+/// A profitable (or not) loop through three markets
+#[derive(Debug, Clone)]
+pub struct Loop {
+    pub assets: [Symbol; 3],    // A, B, C of the loop A->B->C->A
+    pub markets: [Symbol; 3],   // markets of the legs A->B, B->C, C->A
+    pub gross: f64,             // profit without fees in percent
+    pub net: f64,               // profit after fees in percent
+}
+
+/// Market quotes as `(market, base, quote, bid, ask)`, NAN bid/ask if unknown
+pub type Quote<'a> = (&'a Symbol, &'a Symbol, &'a Symbol, f64, f64);
+
+/// Find all loops, most profitable (after `fee_pct` per leg) first
+pub fn scan<'a, I>(quotes: I, fee_pct: f64) -> Vec<Loop> where I: Iterator<Item=Quote<'a>> {
+    let mut legs: HashMap<Symbol, Vec<Leg>> = HashMap::new();
+    for (market, base, quote, bid, ask) in quotes {
+        if !(bid > 0.0 && bid.is_finite() && ask > 0.0 && ask.is_finite()) { continue; }
+        legs.entry(base.clone()).or_default().push(Leg { to: quote.clone(), market: market.clone(), rate: bid });
+        legs.entry(quote.clone()).or_default().push(Leg { to: base.clone(), market: market.clone(), rate: 1.0/ask });
+    }
+    let keep = (1.0-fee_pct/100.0).powi(3);
+    let mut loops = Vec::new();
+    for (a, a_legs) in legs.iter() {
+        for ab in a_legs.iter() {
+            // only loops that start at their smallest asset, so that each loop is found once
+            if *ab.to <= **a { continue; }
+            for bc in legs[&ab.to].iter() {
+                if *bc.to <= **a { continue; }
+                let ca = match legs[&bc.to].iter().find(|l| l.to == *a) {
+                    Some(ca) => ca,
+                    None => continue
+                };
+                let product = ab.rate*bc.rate*ca.rate;
+                loops.push(Loop {
+                    assets: [a.clone(), ab.to.clone(), bc.to.clone()],
+                    markets: [ab.market.clone(), bc.market.clone(), ca.market.clone()],
+                    gross: (product-1.0)*100.0,
+                    net: (product*keep-1.0)*100.0,
+                });
+            }
+        }
+    }
+    loops.sort_by(|x, y| y.net.partial_cmp(&x.net).unwrap_or(std::cmp::Ordering::Equal));
+    loops
+}
+
+/// Find all loops of the live `markets`, most profitable first
+pub fn scan_markets(infos: &[Info], markets: &HashMap<Symbol, MarketState>, fee_pct: f64) -> Vec<Loop> {
+    scan(infos.iter().filter_map(|info| {
+        markets.get(&info.symbol).map(|mkt| {
+            let (bid, ask) = (dec_to_f64(mkt.bid()), dec_to_f64(mkt.ask()));
+            let (bid, ask) = if bid.is_finite() && ask.is_finite() { (bid, ask) }
+                             else { (dec_to_f64(mkt.px()), dec_to_f64(mkt.px())) };
+            (&info.symbol, &info.base, &info.quote, bid, ask)
+        })
+    }), fee_pct)
+}
+
+#[test]
+fn test_loops() {
+    let s = mock_symbol;
+    let quotes = [
+        (s("BTCUSDT"), s("BTC"), s("USDT"), 40000.0, 40000.0),
+        (s("ETHBTC"),  s("ETH"), s("BTC"),  0.05,    0.05),
+        (s("ETHUSDT"), s("ETH"), s("USDT"), 2020.0,  2020.0),
+        (s("DOTUSDT"), s("DOT"), s("USDT"), 5.0,     5.0),
+    ];
+    let loops = scan(quotes.iter().map(|(m, b, q, bid, ask)| (m, b, q, *bid, *ask)), 0.1);
+    assert_eq!(loops.len(), 2);
+    // BTC->ETH->USDT->BTC: 1/0.05*2020/40000 = 1.01
+    assert_eq!(loops[0].assets, [s("BTC"), s("ETH"), s("USDT")]);
+    assert_eq!(loops[0].markets, [s("ETHBTC"), s("ETHUSDT"), s("BTCUSDT")]);
+    assert!((loops[0].gross-1.0).abs() < 1e-9);
+    assert!((loops[0].net-(1.01*0.999f64.powi(3)-1.0)*100.0).abs() < 1e-9);
+    assert!(loops[1].gross < 0.0);
+}
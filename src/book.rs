@@ -0,0 +1,196 @@
+//! Local order book that is maintained from a REST depth snapshot and the diff depth websocket stream
+//!
+//! See: https://binance-docs.github.io/apidocs/spot/en/#how-to-manage-a-local-order-book-correctly
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::ui::Msg;
+use crate::stream;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use tokio::sync::mpsc::UnboundedSender;
+use dec::Decimal64;
+
+/// Number of levels requested in the depth snapshot
+const DEPTH_SNAPSHOT_LIMIT: u32 = 1000;
+
+/// Binance diff depth stream endpoint, `{}` is the lowercase symbol
+const URI_WS_DEPTH: &str = "wss://stream.binance.com:9443/ws/{}@depth@100ms";
+
+/// Price as key of the `BTreeMap`s in `OrderBook`. Prices are never NAN, so the ordering is total.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Price(pub Decimal64);
+
+impl Eq for Price { }
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Reasons why an `OrderBook` lost sync with the stream and needs a new snapshot
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookError {
+    /// The first event after the snapshot does not contain `last_update_id+1`
+    Stale,
+    /// The event's first update id does not follow the previous event's final update id
+    Gap(u64, u64),  // expected, received
+}
+
+/// The local order book of a symbol
+pub struct OrderBook {
+    pub symbol: Symbol,
+    pub ts: u64,                                // ts of last applied update (millis)
+    last_update_id: Option<u64>,                // `None` until the snapshot has been applied
+    bids: BTreeMap<Price, Decimal64>,
+    asks: BTreeMap<Price, Decimal64>,
+    pending: Vec<DepthUpdate>,                  // updates received before the snapshot
+}
+
+impl OrderBook {
+    /// Create new empty `OrderBook` that waits for its snapshot
+    pub fn new(symbol: Symbol) -> Self {
+        OrderBook {
+            symbol: symbol, ts: 0, last_update_id: None,
+            bids: BTreeMap::new(), asks: BTreeMap::new(), pending: Vec::new()
+        }
+    }
+    /// `true` once the snapshot has been applied
+    pub fn is_synced(self: &Self) -> bool {
+        self.last_update_id.is_some()
+    }
+    /// Initialise the book with a depth snapshot and apply the buffered updates.
+    pub fn apply_snapshot(self: &mut Self, snapshot: DepthSnapshot) -> Result<(), BookError> {
+        self.bids.clear();
+        self.asks.clear();
+        for (px, qty) in snapshot.bids.into_iter() { self.bids.insert(Price(px), qty); }
+        for (px, qty) in snapshot.asks.into_iter() { self.asks.insert(Price(px), qty); }
+        self.last_update_id = Some(snapshot.last_update_id);
+        let pending = std::mem::take(&mut self.pending);
+        for update in pending.iter() {
+            self.apply(update)?;
+        }
+        Ok(())
+    }
+    /// Apply a diff depth update.
+    ///
+    /// Updates are buffered until the snapshot arrives, outdated updates are dropped.
+    /// An `Err` means the book is out of sync and needs a new snapshot.
+    pub fn apply(self: &mut Self, update: &DepthUpdate) -> Result<(), BookError> {
+        if update.symbol != self.symbol { return Ok(()); }
+        let last_update_id = match self.last_update_id {
+            Some(id) => id,
+            None => {
+                self.pending.push(update.clone());
+                return Ok(());
+            }
+        };
+        if update.last_id <= last_update_id { return Ok(()); }     // already in the snapshot
+        if update.first_id > last_update_id+1 {
+            return Err(if self.ts == 0 { BookError::Stale } else { BookError::Gap(last_update_id+1, update.first_id) });
+        }
+        for (px, qty) in update.bids.iter() { OrderBook::set_level(&mut self.bids, *px, *qty); }
+        for (px, qty) in update.asks.iter() { OrderBook::set_level(&mut self.asks, *px, *qty); }
+        self.last_update_id = Some(update.last_id);
+        self.ts = update.ts;
+        Ok(())
+    }
+    /// helper function for `apply`, quantity 0 removes the level
+    fn set_level(levels: &mut BTreeMap<Price, Decimal64>, px: Decimal64, qty: Decimal64) {
+        if qty.is_zero() { levels.remove(&Price(px)); }
+        else { levels.insert(Price(px), qty); }
+    }
+    /// Best `n` bids, highest price first
+    pub fn bids(self: &Self, n: usize) -> Vec<Level> {
+        self.bids.iter().rev().take(n).map(|(px, qty)| (px.0, *qty)).collect()
+    }
+    /// Best `n` asks, lowest price first
+    pub fn asks(self: &Self, n: usize) -> Vec<Level> {
+        self.asks.iter().take(n).map(|(px, qty)| (px.0, *qty)).collect()
+    }
+    /// Best bid and best ask
+    pub fn best(self: &Self) -> Option<(Decimal64, Decimal64)> {
+        match (self.bids.keys().next_back(), self.asks.keys().next()) {
+            (Some(bid), Some(ask)) => Some((bid.0, ask.0)),
+            _ => None
+        }
+    }
+    /// Spread (ask-bid) and spread in basis points of the mid price
+    pub fn spread(self: &Self) -> Option<(Decimal64, Decimal64)> {
+        self.best().map(|(bid, ask)| {
+            let spread = ask-bid;
+            let mid = (ask+bid)/Decimal64::from(2);
+            (spread, spread/mid*Decimal64::from(10000))
+        })
+    }
+}
+
+/// Diff depth stream for `symbol`.
+///
+/// Connects the websocket first and then gets the snapshot, so that no update is missed.
+/// The snapshot is sent as `Msg::DepthSnapshot`, the updates as `Msg::Depth`.
+pub async fn depth_stream(symbol: Symbol, ui_tx: UnboundedSender<Msg>) -> Result<(), String> {
+    let uri = URI_WS_DEPTH.replace("{}", &symbol.to_lowercase());
+    let read = match stream::connect(&uri, &ui_tx).await? {
+        Some(read) => read,
+        None => return Ok(())
+    };
+    match get_depth(&symbol, DEPTH_SNAPSHOT_LIMIT).await {
+        Ok(snapshot) => {
+            ui_tx.send(Msg::DepthSnapshot(symbol, snapshot)).map_err(|e| format!("UI failed: {:?}", e))?;
+        },
+        Err(e) => {
+            ui_tx.send(Msg::Msg(format!("Failed to get depth: {:?}", e))).map_err(|e| format!("UI failed: {:?}", e))?;
+            return Ok(());
+        }
+    }
+    stream::forward(read, ui_tx, Msg::Depth).await
+}
+
+/// Test fixture: `DepthUpdate` of BTCUSDT with the ids `first_id..=last_id`
+#[cfg(test)]
+fn mock_depth_update(first_id: u64, last_id: u64, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> DepthUpdate {
+    let levels = |l: &[(&str, &str)]| l.iter().map(|(px, qty)| (parse_dec(&px.to_string()), parse_dec(&qty.to_string()))).collect();
+    DepthUpdate { symbol: mock_symbol("BTCUSDT"), ts: last_id, first_id: first_id, last_id: last_id,
+                  bids: levels(bids), asks: levels(asks) }
+}
+
+#[test]
+fn test_sequence() {
+    let mut book = OrderBook::new(mock_symbol("BTCUSDT"));
+    // buffered before the snapshot, the first one is outdated
+    assert_eq!(book.apply(&mock_depth_update(90, 99, &[("9.0", "5.0")], &[])), Ok(()));
+    assert_eq!(book.apply(&mock_depth_update(100, 102, &[("10.0", "0.00000000")], &[("11.5", "3.0")])), Ok(()));
+    assert!(!book.is_synced());
+    let snapshot = DepthSnapshot {
+        last_update_id: 100,
+        bids: vec![(parse_dec(&String::from("10.0")), parse_dec(&String::from("1.0"))),
+                   (parse_dec(&String::from("9.5")), parse_dec(&String::from("2.0")))],
+        asks: vec![(parse_dec(&String::from("11.0")), parse_dec(&String::from("1.0")))],
+    };
+    assert_eq!(book.apply_snapshot(snapshot), Ok(()));
+    assert_eq!(book.bids(10).len(), 1);
+    assert_eq!(book.bids(10)[0].0, parse_dec(&String::from("9.5")));
+    assert_eq!(book.asks(10).len(), 2);
+    assert_eq!(book.best(), Some((parse_dec(&String::from("9.5")), parse_dec(&String::from("11.0")))));
+    // continuous
+    assert_eq!(book.apply(&mock_depth_update(103, 105, &[], &[("11.0", "0.0")])), Ok(()));
+    assert_eq!(book.asks(10)[0].0, parse_dec(&String::from("11.5")));
+    // gap
+    assert_eq!(book.apply(&mock_depth_update(107, 110, &[], &[])), Err(BookError::Gap(106, 107)));
+}
+
+#[test]
+fn test_stale_snapshot() {
+    let mut book = OrderBook::new(mock_symbol("BTCUSDT"));
+    assert_eq!(book.apply(&mock_depth_update(120, 130, &[], &[])), Ok(()));
+    let snapshot = DepthSnapshot { last_update_id: 100, bids: vec![], asks: vec![] };
+    assert_eq!(book.apply_snapshot(snapshot), Err(BookError::Stale));
+}
@@ -0,0 +1,148 @@
+//! On-disk cache of klines
+//!
+//! The bars of each (exchange, symbol, interval) are stored in a CSV file
+//! `<dir>/<exchange>/<SYMBOL>-<interval>.csv` with lines `t,o,h,l,c,v`. On a refresh only the
+//! bars from the last cached bar onwards are downloaded, the last bar is refetched because it was
+//! probably not complete. While the last cached bar is still open nothing is downloaded. If the
+//! download fails the cached bars are used.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use std::fs;
+use std::path::PathBuf;
+
+/// The exchange all klines come from
+pub const EXCHANGE: &str = "binance";
+
+/// Maximum number of bars kept in a cache file
+const CACHE_MAX_BARS: usize = 10_000;
+
+/// Number of bars returned, same as a single `get_klines`
+const KLINES_LIMIT: usize = 1000;
+
+/// Kline cache in a directory
+#[derive(Debug, Clone)]
+pub struct KlineCache {
+    dir: PathBuf,
+}
+
+impl KlineCache {
+    pub fn new(dir: PathBuf) -> Self {
+        KlineCache { dir: dir }
+    }
+    /// `$XDG_CACHE_HOME/coinlive` or `$HOME/.cache/coinlive`
+    pub fn default_dir() -> Option<PathBuf> {
+        match std::env::var_os("XDG_CACHE_HOME") {
+            Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir).join("coinlive")),
+            _ => std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("coinlive"))
+        }
+    }
+    /// Path of the cache file
+    fn path(self: &Self, symbol: &Symbol, interval: &Interval) -> PathBuf {
+        // 1M would clash with 1m on case insensitive file systems
+        let interval = if *interval == Interval::I1M { "1mo" } else { interval.str() };
+        self.dir.join(EXCHANGE).join(format!("{}-{}.csv", symbol, interval))
+    }
+    /// Cached bars, empty if there are none or the file is unreadable
+    pub fn load(self: &Self, symbol: &Symbol, interval: &Interval) -> Vec<Bar> {
+        let text = match fs::read_to_string(self.path(symbol, interval)) {
+            Ok(text) => text,
+            Err(_) => return Vec::new()
+        };
+        let parse = |line: &str| -> Option<Bar> {
+            let mut it = line.split(',');
+            Some(Bar { t: it.next()?.parse().ok()?, o: it.next()?.parse().ok()?, h: it.next()?.parse().ok()?,
+                       l: it.next()?.parse().ok()?, c: it.next()?.parse().ok()?, v: it.next()?.parse().ok()? })
+        };
+        text.lines().map(parse).collect::<Option<Vec<Bar>>>().unwrap_or_default()
+    }
+    /// Store `bars`, keeping at most `CACHE_MAX_BARS`
+    pub fn save(self: &Self, symbol: &Symbol, interval: &Interval, bars: &[Bar]) -> Result<(), String> {
+        let path = self.path(symbol, interval);
+        let err = |e: std::io::Error| format!("Failed to write kline cache {}: {}", path.display(), e);
+        if let Some(dir) = path.parent() { fs::create_dir_all(dir).map_err(err)?; }
+        let bars = &bars[bars.len().saturating_sub(CACHE_MAX_BARS)..];
+        let text: String = bars.iter().map(|b| format!("{},{},{},{},{},{}\n", b.t, b.o, b.h, b.l, b.c, b.v)).collect();
+        // write to a temporary file first, so that the cache file is never half written
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, text).map_err(err)?;
+        fs::rename(&tmp, &path).map_err(err)
+    }
+}
+
+/// Append `new` bars to `cached` ones, the new bars replace cached bars with the same or later open time
+pub fn merge(mut cached: Vec<Bar>, new: Vec<Bar>) -> Vec<Bar> {
+    if let Some(first) = new.first() {
+        let t = first.t;
+        cached.retain(|b| b.t < t);
+    }
+    cached.extend(new);
+    cached
+}
+
+/// Klines for a symbol, using the `cache` if there is one.
+///
+/// Returns the last 1000 bars and whether they are only from the cache because the download failed.
+pub async fn get_klines_cached(cache: Option<&KlineCache>, symbol: &Symbol, interval: &Interval)
+        -> Result<(Vec<Bar>, bool), Box<dyn std::error::Error>> {
+    let cache = match cache {
+        Some(cache) => cache,
+        None => return get_klines(symbol, interval).await.map(|bars| (bars, false))
+    };
+    let cached = cache.load(symbol, interval);
+    let now = now_timestamp();
+    // the last cached bar is still open, nothing newer to download
+    if cached.last().map(|b| b.t+interval.seconds() as u64*1000 > now).unwrap_or(false) {
+        let n = cached.len();
+        return Ok((cached.into_iter().skip(n.saturating_sub(KLINES_LIMIT)).collect(), false));
+    }
+    // if the cache is too old to be continued with a single request start from scratch
+    let horizon = now.saturating_sub(KLINES_LIMIT as u64*interval.seconds() as u64*1000);
+    let start = cached.last().map(|b| b.t).filter(|t| *t > horizon);
+    let bars = match get_klines_since(symbol, interval, start).await {
+        Ok(new) => {
+            let bars = if start.is_some() { merge(cached, new) } else { new };
+            // a cache that can't be written must not break the graph
+            let _ = cache.save(symbol, interval, &bars);
+            bars
+        },
+        Err(e) => {
+            if cached.is_empty() { return Err(e); }
+            let n = cached.len();
+            return Ok((cached.into_iter().skip(n.saturating_sub(KLINES_LIMIT)).collect(), true));
+        }
+    };
+    let n = bars.len();
+    Ok((bars.into_iter().skip(n.saturating_sub(KLINES_LIMIT)).collect(), false))
+}
+
+#[test]
+fn test_cache() -> Result<(), Box<dyn std::error::Error>> {
+    let bar = |t: u64, c: f32| Bar { t: t, o: c, h: c, l: c, c: c, v: 0.5 };
+    let merged = merge(vec![bar(0, 1.0), bar(1, 2.0), bar(2, 3.0)], vec![bar(2, 3.5), bar(3, 4.0)]);
+    assert_eq!(merged, vec![bar(0, 1.0), bar(1, 2.0), bar(2, 3.5), bar(3, 4.0)]);
+    assert_eq!(merge(vec![bar(0, 1.0)], vec![]), vec![bar(0, 1.0)]);
+    let dir = std::env::temp_dir().join(format!("coinlive-cache-{}", std::process::id()));
+    let cache = KlineCache::new(dir.clone());
+    let symbol = mock_symbol("BTCUSDT");
+    assert!(cache.load(&symbol, &Interval::I1h).is_empty());
+    cache.save(&symbol, &Interval::I1h, &merged)?;
+    assert_eq!(cache.load(&symbol, &Interval::I1h), merged);
+    assert!(cache.load(&symbol, &Interval::I1d).is_empty());
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_open_bar() -> Result<(), Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("coinlive-cache-open-{}", std::process::id()));
+    let cache = KlineCache::new(dir.clone());
+    // an unknown symbol, so that a download would fail
+    let symbol = mock_symbol("NOSUCHSYMBOL");
+    let t = now_timestamp()/3_600_000*3_600_000;
+    let bars = vec![Bar { t: t-3_600_000, o: 1.0, h: 1.0, l: 1.0, c: 1.0, v: 1.0 }, Bar { t: t, o: 1.0, h: 2.0, l: 1.0, c: 2.0, v: 1.0 }];
+    cache.save(&symbol, &Interval::I1h, &bars)?;
+    assert_eq!(get_klines_cached(Some(&cache), &symbol, &Interval::I1h).await?, (bars, false));
+    fs::remove_dir_all(dir)?;
+    Ok(())
+}
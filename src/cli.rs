@@ -0,0 +1,480 @@
+//! Subcommands that run without the terminal UI
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::cache::{KlineCache, get_klines_cached};
+use crate::export::{self, Format, SnapshotRow, UpdateRow};
+use crate::rates::Rates;
+use crate::stream;
+use crate::http;
+use crate::live::Live;
+use crate::{api, fanout, metrics};
+use crate::ui::{Msg, MarketState, ansi, graph::Graph};
+use serde::Serialize;
+use clap::ArgMatches;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use tokio::sync::mpsc;
+use tui::{buffer::Buffer, layout::Rect, style::{Color, Style}, widgets::Widget};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Result type of the subcommands
+type CmdResult = Result<(), Box<dyn std::error::Error>>;
+
+/// The kline cache given with `--cache-dir`, or the default one unless `--no-cache`
+pub fn kline_cache(m: &ArgMatches) -> Option<KlineCache> {
+    if m.get_flag("no-cache") { return None; }
+    m.get_one::<String>("cache-dir").map(PathBuf::from).or_else(KlineCache::default_dir).map(KlineCache::new)
+}
+
+/// Format given with `--format`, or from the extension of `output`
+fn format_of(m: &ArgMatches, output: &str) -> Result<Format, String> {
+    match m.get_one::<String>("format") {
+        Some(format) => format.parse(),
+        None => Format::from_path(output)
+    }
+}
+
+/// `coinlive export klines SYMBOL` and `coinlive export snapshot`
+pub async fn export(m: &ArgMatches) -> CmdResult {
+    match m.subcommand() {
+        Some(("klines", m)) => {
+            let symbol = parse_symbol(&m.get_one::<String>("symbol").expect("required").to_uppercase())?;
+            let interval: Interval = m.get_one::<String>("interval").expect("has default").parse()?;
+            let output = m.get_one::<String>("output").expect("required");
+            let format = format_of(m, output)?;
+            let (bars, offline) = get_klines_cached(kline_cache(m).as_ref(), &symbol, &interval).await?;
+            export::write_klines(output, format, &bars)?;
+            eprintln!("Exported {} {} klines of {} to {}{}", bars.len(), interval, symbol, output,
+                      if offline { " (cached, offline)" } else { "" });
+        },
+        Some(("snapshot", m)) => {
+            let output = m.get_one::<String>("output").expect("required");
+            let format = format_of(m, output)?;
+            let infos = sort_infos(get_infos().await?);
+            let markets = get_markets()?;
+            let rows: Vec<SnapshotRow> = infos.iter().filter_map(|info| markets.get(&info.symbol).map(|mkt| {
+                let px = dec_to_f64(mkt.price);
+                let change = dec_to_f64(mkt.price_change);
+                SnapshotRow { symbol: info.symbol.to_string(), base: info.base.to_string(), quote: info.quote.to_string(),
+                              price: px, change_24h: change_24h(px, change), volume: dec_to_f64(info.volume) }
+            })).collect();
+            export::write_snapshot(output, format, &rows)?;
+            eprintln!("Exported {} prices to {}", rows.len(), output);
+        },
+        _ => unreachable!("subcommand required")
+    }
+    Ok(())
+}
+
+/// Price of a single pair, for output
+#[derive(Debug, Clone, Serialize)]
+struct PriceRow {
+    symbol: String,             // the symbol as given on the command line
+    base: String,
+    quote: String,
+    price: f64,
+    change_24h: Option<f64>,    // percent, only for pairs with a market
+    #[serde(skip)]
+    price_str: String,          // price formatted with its full precision
+}
+
+/// Format `x` with 8 significant digits, without trailing zeros
+fn fmt_significant(x: f64) -> String {
+    if !x.is_finite() || x == 0.0 { return format!("{}", x); }
+    let decimals = (7 - x.abs().log10().floor() as i32).max(0) as usize;
+    let s = format!("{:.*}", decimals, x);
+    if s.contains('.') { s.trim_end_matches('0').trim_end_matches('.').to_string() } else { s }
+}
+
+/// Fill the placeholders `{symbol}`, `{base}`, `{quote}`, `{price}` and `{change}` of `template`
+fn fill_template(template: &str, row: &PriceRow) -> String {
+    let change = row.change_24h.map(|c| format!("{:+.2}", c)).unwrap_or_else(|| String::from("-"));
+    template.replace("{symbol}", &row.symbol).replace("{base}", &row.base).replace("{quote}", &row.quote)
+            .replace("{price}", &row.price_str).replace("{change}", &change)
+}
+
+/// Price change over 24h in percent of the price `px` that changed by `change`, `None` without open price
+fn change_24h(px: f64, change: f64) -> Option<f64> {
+    let open = px-change;
+    if open == 0.0 { None } else { Some(change/open*100.0) }
+}
+
+/// `coinlive price SYMBOL...`
+pub async fn price(m: &ArgMatches) -> CmdResult {
+    let default_quote = m.get_one::<String>("quote").expect("has default").to_uppercase();
+    let infos = sort_infos(get_infos().await?);
+    let markets = get_markets()?;
+    let prices: Vec<(&Symbol, &Symbol, f64)> = infos.iter()
+        .filter_map(|i| markets.get(&i.symbol).map(|mkt| (&i.base, &i.quote, dec_to_f64(mkt.price))))
+        .collect();
+    let rates = Rates::from_prices(prices.into_iter());
+    let mut rows: Vec<PriceRow> = Vec::new();
+    let mut unknown: Vec<String> = Vec::new();
+    for arg in m.get_many::<String>("symbols").expect("required") {
+        let upper = arg.to_uppercase();
+        // BASE/QUOTE, a market symbol like ETHBTC, or an asset priced in the default quote
+        let (base, quote) = match upper.split_once('/') {
+            Some((base, quote)) => (String::from(base), String::from(quote)),
+            None => match infos.iter().find(|i| i.symbol == upper.as_str()) {
+                Some(info) => (info.base.to_string(), info.quote.to_string()),
+                None => (upper.clone(), default_quote.clone())
+            }
+        };
+        let (b, q) = match (parse_symbol(&base), parse_symbol(&quote)) {
+            (Ok(b), Ok(q)) => (b, q),
+            _ => { unknown.push(arg.clone()); continue; }
+        };
+        let direct = infos.iter().find(|i| i.base == b && i.quote == q).and_then(|i| markets.get(&i.symbol));
+        let row = match direct {
+            Some(mkt) => {
+                let (px, change) = (dec_to_f64(mkt.price), dec_to_f64(mkt.price_change));
+                Some(PriceRow { symbol: arg.clone(), base: base, quote: quote, price: px,
+                                change_24h: change_24h(px, change), price_str: fmt_dec(mkt.price) })
+            },
+            None => rates.rate(&b, &q).map(|px| PriceRow {
+                symbol: arg.clone(), base: base, quote: quote, price: px, change_24h: None, price_str: fmt_significant(px)
+            })
+        };
+        match row {
+            Some(row) => rows.push(row),
+            None => unknown.push(arg.clone())
+        }
+    }
+    if m.get_flag("json") {
+        println!("{}", serde_json::to_string_pretty(&rows)?);
+    } else {
+        let template = m.get_one::<String>("format").map(|s| s.as_str()).unwrap_or("{base}/{quote} {price} {change}%");
+        for row in rows.iter() {
+            println!("{}", fill_template(template, row));
+        }
+    }
+    if !unknown.is_empty() {
+        return Err(format!("No price for {}", unknown.join(", ")).into());
+    }
+    Ok(())
+}
+
+/// Market symbols of `args`, a symbol without quote currency implies `USDT`
+fn market_symbols<'a>(infos: &[Info], args: impl Iterator<Item=&'a String>) -> Result<Vec<Symbol>, String> {
+    args.map(|arg| {
+        let upper = arg.to_uppercase();
+        let usdt = format!("{}USDT", upper);
+        infos.iter().find(|i| i.symbol == upper.as_str()).or_else(|| infos.iter().find(|i| i.symbol == usdt.as_str()))
+             .map(|i| i.symbol.clone()).ok_or(format!("Unknown symbol {}", arg))
+    }).collect()
+}
+
+/// `coinlive stream`
+pub async fn stream(m: &ArgMatches) -> CmdResult {
+    let csv = m.get_one::<String>("format").expect("has default") == "csv";
+    let symbols: Option<Vec<Symbol>> = match m.get_many::<String>("symbols") {
+        Some(args) => Some(market_symbols(&get_infos().await?, args)?),
+        None => None
+    };
+    let (tx, mut rx) = mpsc::unbounded_channel::<Msg>();
+    tokio::spawn(stream::ws_reconnect(stream::URI_WS_TICKER, tx, Msg::WS));
+    let mut out = std::io::stdout().lock();
+    let mut buf: Vec<Update> = Vec::new();
+    // a closed stdout, e.g. by `head`, ends the stream
+    if csv && writeln!(out, "symbol,ts,price,price_24h,change_24h").is_err() { return Ok(()); }
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            Msg::WS(_, msg) => {
+                buf.clear();
+                if parse_updates(&msg, &mut buf).is_err() { continue; }
+                for update in buf.iter().filter(|u| symbols.as_ref().map(|s| s.contains(&u.symbol)).unwrap_or(true)) {
+                    let row = UpdateRow::new(update);
+                    let line = if csv { row.csv() } else { serde_json::to_string(&row)? };
+                    if writeln!(out, "{}", line).is_err() { return Ok(()); }
+                }
+                if out.flush().is_err() { return Ok(()); }
+            },
+            Msg::Msg(msg) => eprintln!("{}", msg),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Parse a UTC time like `2024-01-01`, `2024-01-01 12:00` or RFC 3339 into millis
+fn parse_time(s: &str) -> Result<u64, String> {
+    let millis = if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        t.timestamp_millis()
+    } else if let Some(t) = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"].iter()
+            .find_map(|f| NaiveDateTime::parse_from_str(s, f).ok()) {
+        t.and_utc().timestamp_millis()
+    } else if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        d.and_time(NaiveTime::MIN).and_utc().timestamp_millis()
+    } else {
+        return Err(format!("Bad time '{}', expected e.g. 2024-01-01, '2024-01-01 12:00' or 2024-01-01T12:00:00Z", s));
+    };
+    if millis < 0 { return Err(format!("Time before 1970: {}", s)); }
+    Ok(millis as u64)
+}
+
+/// `coinlive klines SYMBOL`
+pub async fn klines(m: &ArgMatches) -> CmdResult {
+    let symbol = parse_symbol(&m.get_one::<String>("symbol").expect("required").to_uppercase())?;
+    let interval: Interval = m.get_one::<String>("interval").expect("has default").parse()?;
+    let to = match m.get_one::<String>("to") {
+        Some(to) => parse_time(to)?,
+        None => now_timestamp()
+    };
+    let from = match m.get_one::<String>("from") {
+        Some(from) => parse_time(from)?,
+        None => to.saturating_sub(1000*interval.seconds() as u64*1000)
+    };
+    if from >= to {
+        return Err("--from must be before --to".into());
+    }
+    let output = m.get_one::<String>("output");
+    let format = match (m.get_one::<String>("format"), output) {
+        (Some(format), _) => format.parse()?,
+        (None, Some(output)) => Format::from_path(output)?,
+        (None, None) => Format::Csv
+    };
+    // bars open before `to`
+    let bars = get_klines_range(&symbol, &interval, from, to-1).await?;
+    match output {
+        Some(output) => {
+            export::write_klines(output, format, &bars)?;
+            eprintln!("Downloaded {} {} klines of {} to {}", bars.len(), interval, symbol, output);
+        },
+        None => export::print_klines(&mut std::io::stdout().lock(), format, &bars)?
+    }
+    Ok(())
+}
+
+/// `coinlive chart SYMBOL`
+pub async fn chart(m: &ArgMatches) -> CmdResult {
+    let symbol = parse_symbol(&m.get_one::<String>("symbol").expect("required").to_uppercase())?;
+    let interval: Interval = m.get_one::<String>("interval").expect("has default").parse()?;
+    let width = *m.get_one::<u16>("width").expect("has default");
+    let height = *m.get_one::<u16>("height").expect("has default");
+    let (bars, _) = get_klines_cached(kline_cache(m).as_ref(), &symbol, &interval).await?;
+    // a graph needs two bars to know the interval
+    if bars.len() < 2 {
+        return Err(format!("Not enough {} klines of {}", interval, symbol).into());
+    }
+    let area = Rect::new(0, 0, width, height);
+    let mut buf = Buffer::empty(area);
+    Graph::new(&Vec::new(), &bars, interval, symbol).render(area, &mut buf);
+    print!("{}", ansi::to_ansi(&buf, !m.get_flag("no-color")));
+    Ok(())
+}
+
+/// `coinlive serve`
+pub async fn serve(m: &ArgMatches) -> CmdResult {
+    let live = Live::shared();
+    if let Some(addr) = m.get_one::<String>("metrics") {
+        tokio::spawn(http::serve(http::bind(addr).await?, metrics::handler(live.clone())));
+        eprintln!("Serving metrics on http://{}/metrics", addr);
+    }
+    if let Some(addr) = m.get_one::<String>("api") {
+        tokio::spawn(http::serve(http::bind(addr).await?, api::handler(live.clone(), kline_cache(m))));
+        eprintln!("Serving the API on http://{}/prices", addr);
+    }
+    if let Some(addr) = m.get_one::<String>("ws-server") {
+        tokio::spawn(fanout::serve(http::bind(addr).await?, live.clone()));
+        eprintln!("Serving price updates on ws://{}/", addr);
+    }
+    let infos = get_infos().await?;
+    if let Ok(mut live) = live.lock() {
+        live.set_infos(&infos);
+    }
+    let (tx, mut rx) = mpsc::unbounded_channel::<Msg>();
+    tokio::spawn(stream::ws_reconnect(stream::URI_WS_TICKER, tx, Msg::WS));
+    let mut buf: Vec<Update> = Vec::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            Msg::WS(ts_rec, msg) => {
+                buf.clear();
+                if parse_updates(&msg, &mut buf).is_err() { continue; }
+                if let Ok(mut live) = live.lock() {
+                    live.update(ts_rec, &buf);
+                }
+            },
+            Msg::Msg(msg) => eprintln!("{}", msg),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Output of `coinlive status`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StatusOutput {
+    Ansi,       // rewrites a single line in the terminal
+    Plain,
+    Tmux,
+    Polybar,
+    I3bar,      // i3bar JSON protocol, also used by sway and i3status-rust
+}
+
+impl std::str::FromStr for StatusOutput {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi" => Ok(StatusOutput::Ansi),
+            "plain" => Ok(StatusOutput::Plain),
+            "tmux" => Ok(StatusOutput::Tmux),
+            "polybar" => Ok(StatusOutput::Polybar),
+            "i3bar" => Ok(StatusOutput::I3bar),
+            _ => Err(format!("Unknown output '{}'", s))
+        }
+    }
+}
+
+/// Hex notation of the up/down colors of `MarketState::style`
+fn hex_color(color: Color) -> Option<&'static str> {
+    match color {
+        Color::Green => Some("#00FF00"),
+        Color::Red => Some("#FF0000"),
+        _ => None
+    }
+}
+
+/// `text` in the foreground color of `style`, with the markup of `output`
+fn paint(text: &str, style: Style, output: StatusOutput) -> String {
+    let (color, hex) = match style.fg.and_then(|c| hex_color(c).map(|hex| (c, hex))) {
+        Some(color) => color,
+        None => return String::from(text)
+    };
+    match output {
+        StatusOutput::Ansi => format!("\x1b[{}m{}\x1b[0m", ansi::color_code(color, false), text),
+        StatusOutput::Tmux => format!("#[fg={}]{}#[default]", hex, text),
+        StatusOutput::Polybar => format!("%{{F{}}}{}%{{F-}}", hex, text),
+        StatusOutput::Plain | StatusOutput::I3bar => String::from(text)
+    }
+}
+
+/// Fill the placeholders `{sym}`, `{symbol}`, `{px}` and `{pct}` of `template`
+fn fill_status(template: &str, info: &Info, mkt: &MarketState) -> String {
+    let change = mkt.change_24h();
+    let pct = if change.is_finite() { format!("{:+.2}%", change) } else { String::from("-") };
+    template.replace("{sym}", info.short_symbol()).replace("{symbol}", &info.symbol)
+            .replace("{px}", &mkt.price_string()).replace("{pct}", &pct)
+}
+
+/// A block of the i3bar protocol
+#[derive(Debug, Clone, Serialize)]
+struct I3barBlock {
+    name: &'static str,
+    instance: String,
+    full_text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'static str>,
+}
+
+/// The status of the `markets` of `infos` that had an update, `None` if there is none yet
+fn status_line(infos: &[Info], markets: &HashMap<Symbol, MarketState>, template: &str, separator: &str,
+               output: StatusOutput) -> Option<String> {
+    let items: Vec<(&Info, &MarketState)> = infos.iter().filter_map(|i| markets.get(&i.symbol).map(|m| (i, m))).collect();
+    if items.is_empty() { return None; }
+    if output == StatusOutput::I3bar {
+        let blocks: Vec<I3barBlock> = items.iter().map(|(info, mkt)| I3barBlock {
+            name: "coinlive", instance: info.symbol.to_string(), full_text: fill_status(template, info, mkt),
+            color: mkt.style().fg.and_then(hex_color)
+        }).collect();
+        return serde_json::to_string(&blocks).ok();
+    }
+    let parts: Vec<String> = items.iter().map(|(info, mkt)| paint(&fill_status(template, info, mkt), mkt.style(), output)).collect();
+    Some(parts.join(separator))
+}
+
+/// `coinlive status`
+pub async fn status(m: &ArgMatches) -> CmdResult {
+    let output: StatusOutput = m.get_one::<String>("output").expect("has default").parse()?;
+    let template = m.get_one::<String>("format").expect("has default");
+    let separator = m.get_one::<String>("separator").expect("has default");
+    let all_infos = get_infos().await?;
+    let symbols = market_symbols(&all_infos, m.get_many::<String>("symbols").expect("has default"))?;
+    let infos: Vec<Info> = symbols.iter().filter_map(|s| all_infos.iter().find(|i| i.symbol == *s).cloned()).collect();
+    let mut markets: HashMap<Symbol, MarketState> = HashMap::new();
+    let (tx, mut rx) = mpsc::unbounded_channel::<Msg>();
+    tokio::spawn(stream::ws_reconnect(stream::URI_WS_TICKER, tx, Msg::WS));
+    let mut out = std::io::stdout().lock();
+    // a closed stdout ends the status, e.g. when the bar is restarted
+    if output == StatusOutput::I3bar && writeln!(out, "{{\"version\":1}}\n[").is_err() { return Ok(()); }
+    let mut buf: Vec<Update> = Vec::new();
+    let mut last = String::new();
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            Msg::WS(_, msg) => {
+                buf.clear();
+                if parse_updates(&msg, &mut buf).is_err() { continue; }
+                for update in buf.iter().filter(|u| symbols.contains(&u.symbol)) {
+                    markets.entry(update.symbol.clone()).or_insert_with(MarketState::new).update(update);
+                }
+                let line = match status_line(&infos, &markets, template, separator, output) {
+                    Some(line) if line != last => line,
+                    _ => continue
+                };
+                let written = match output {
+                    StatusOutput::Ansi => write!(out, "\r{}\x1b[K", line),
+                    StatusOutput::I3bar => writeln!(out, "{},", line),
+                    _ => writeln!(out, "{}", line)
+                };
+                if written.and_then(|_| out.flush()).is_err() { return Ok(()); }
+                last = line;
+            },
+            Msg::Msg(msg) => eprintln!("{}", msg),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_price_format() {
+    assert_eq!(fmt_significant(0.0000123456789), "0.000012345679");
+    assert_eq!(fmt_significant(65432.1), "65432.1");
+    assert_eq!(fmt_significant(123456789.0), "123456789");
+    let row = PriceRow { symbol: String::from("eth/btc"), base: String::from("ETH"), quote: String::from("BTC"),
+                         price: 0.05, change_24h: None, price_str: String::from("0.05") };
+    assert_eq!(fill_template("{symbol}: {base}/{quote} {price} {change}%", &row), "eth/btc: ETH/BTC 0.05 -%");
+    let row = PriceRow { change_24h: Some(1.234), ..row };
+    assert_eq!(fill_template("{change}", &row), "+1.23");
+    assert_eq!(change_24h(150.0, 50.0), Some(50.0));
+    assert_eq!(change_24h(2.0, 2.0), None);
+}
+
+#[test]
+fn test_stream_symbols() {
+    let infos = vec![mock_info("BTC", "USDT", "1"), mock_info("ETH", "BTC", "1")];
+    let args: Vec<String> = vec![String::from("btc"), String::from("ETHBTC")];
+    assert_eq!(market_symbols(&infos, args.iter()), Ok(vec![mock_symbol("BTCUSDT"), mock_symbol("ETHBTC")]));
+    let args: Vec<String> = vec![String::from("ETH")];
+    assert!(market_symbols(&infos, args.iter()).is_err());
+}
+
+#[test]
+fn test_klines_time() {
+    assert_eq!(parse_time("2024-01-01"), Ok(1704067200000));
+    assert_eq!(parse_time("2024-01-01 01:00"), Ok(1704070800000));
+    assert_eq!(parse_time("2024-01-01T01:00:00+01:00"), Ok(1704067200000));
+    assert!(parse_time("01/01/2024").is_err());
+}
+
+#[test]
+fn test_status() {
+    let infos = vec![mock_info("BTC", "USDT", "1"), mock_info("ETH", "BTC", "1")];
+    let mut markets = HashMap::new();
+    assert_eq!(status_line(&infos, &markets, "{sym}", " ", StatusOutput::Plain), None);
+    let mut btc = MarketState::new();
+    btc.update(&mock_update("BTCUSDT", 1, "100", "100"));
+    btc.update(&mock_update("BTCUSDT", 1, "150", "100"));
+    markets.insert(mock_symbol("BTCUSDT"), btc);
+    let mut eth = MarketState::new();
+    eth.update(&mock_update("ETHBTC", 1, "50", "100"));
+    markets.insert(mock_symbol("ETHBTC"), eth);
+    assert_eq!(status_line(&infos, &markets, "{sym} {px} {pct}", " | ", StatusOutput::Plain),
+               Some(String::from("BTC 150 +50.00% | ETHBTC 50 -50.00%")));
+    assert_eq!(status_line(&infos, &markets, "{symbol}", " ", StatusOutput::Tmux),
+               Some(String::from("#[fg=#00FF00]BTCUSDT#[default] ETHBTC")));
+    assert_eq!(status_line(&infos, &markets, "{sym}", " ", StatusOutput::Ansi), Some(String::from("\x1b[32mBTC\x1b[0m ETHBTC")));
+    assert_eq!(status_line(&infos[..1], &markets, "{sym}", " ", StatusOutput::I3bar),
+               Some(String::from("[{\"name\":\"coinlive\",\"instance\":\"BTCUSDT\",\"full_text\":\"BTC\",\"color\":\"#00FF00\"}]")));
+}
@@ -0,0 +1,271 @@
+//! Export of klines and market snapshots to CSV, JSON and Parquet
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::ui::MarketState;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::Arc;
+use parquet::{
+    data_type::{ByteArray, ByteArrayType, DoubleType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::parser::parse_message_type,
+};
+
+/// CSV header of the klines
+const KLINES_HEADER: &str = "open_time,open,high,low,close,volume";
+
+/// Parquet schema of the klines
+const KLINES_SCHEMA: &str = "message klines {
+    REQUIRED INT64 open_time (TIMESTAMP(MILLIS,true));
+    REQUIRED DOUBLE open;
+    REQUIRED DOUBLE high;
+    REQUIRED DOUBLE low;
+    REQUIRED DOUBLE close;
+    REQUIRED DOUBLE volume;
+}";
+
+/// Parquet schema of the snapshot
+const SNAPSHOT_SCHEMA: &str = "message snapshot {
+    REQUIRED BYTE_ARRAY symbol (UTF8);
+    REQUIRED BYTE_ARRAY base (UTF8);
+    REQUIRED BYTE_ARRAY quote (UTF8);
+    REQUIRED DOUBLE price;
+    OPTIONAL DOUBLE change_24h;
+    REQUIRED DOUBLE volume;
+}";
+
+/// Export file format
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Json,
+    Parquet,
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "json" => Ok(Format::Json),
+            "parquet" => Ok(Format::Parquet),
+            _ => Err(format!("Unknown format '{}', expected csv, json or parquet", s))
+        }
+    }
+}
+
+impl Format {
+    /// Format from the extension of `path`
+    pub fn from_path(path: &str) -> Result<Format, String> {
+        match std::path::Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some(ext) => ext.parse(),
+            None => Err(format!("Can't tell the format of '{}', use the extension .csv, .json or .parquet", path))
+        }
+    }
+}
+
+/// A single kline, for export
+#[derive(Debug, Clone, Serialize)]
+pub struct KlineRow {
+    pub open_time: u64,     // millis
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+impl KlineRow {
+    pub fn new(bar: &Bar) -> Self {
+        KlineRow { open_time: bar.t, open: bar.o as f64, high: bar.h as f64, low: bar.l as f64, close: bar.c as f64, volume: bar.v as f64 }
+    }
+    fn csv(self: &Self) -> String {
+        format!("{},{},{},{},{},{}", self.open_time, self.open, self.high, self.low, self.close, self.volume)
+    }
+}
+
+/// Price and 24h change of a single market, for export
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotRow {
+    pub symbol: String,
+    pub base: String,
+    pub quote: String,
+    pub price: f64,
+    pub change_24h: Option<f64>,    // percent, None without price 24h ago
+    pub volume: f64,        // 24h volume in USDT
+}
+
+/// A price update of the live feed, for output
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateRow {
+    pub symbol: String,
+    pub ts: u64,            // exchange timestamp (millis)
+    pub price: f64,
+    pub price_24h: f64,     // price 24h ago
+    pub change_24h: f64,    // percent
+}
+
+impl UpdateRow {
+    pub fn new(update: &Update) -> Self {
+        let (px, px_24h) = (dec_to_f64(update.px), dec_to_f64(update.px_24h));
+        UpdateRow { symbol: update.symbol.to_string(), ts: update.ts, price: px, price_24h: px_24h,
+                    change_24h: (px/px_24h-1.0)*100.0 }
+    }
+    pub fn csv(self: &Self) -> String {
+        format!("{},{},{},{},{}", self.symbol, self.ts, self.price, self.price_24h, self.change_24h)
+    }
+}
+
+/// Snapshot rows of the live `markets`, in the order of `infos`
+pub fn snapshot_rows(infos: &[Info], markets: &HashMap<Symbol, MarketState>) -> Vec<SnapshotRow> {
+    infos.iter().filter_map(|info| markets.get(&info.symbol).map(|mkt| SnapshotRow {
+        symbol: info.symbol.to_string(), base: info.base.to_string(), quote: info.quote.to_string(),
+        price: dec_to_f64(mkt.px()), change_24h: Some(mkt.change_24h()).filter(|c| c.is_finite()), volume: dec_to_f64(info.volume)
+    })).collect()
+}
+
+/// Write `rows` as JSON array to `w`
+fn json_to<T: Serialize>(w: &mut impl Write, rows: &[T]) -> std::io::Result<()> {
+    serde_json::to_writer_pretty(&mut *w, rows)?;
+    writeln!(w)?;
+    w.flush()
+}
+
+/// Write `header` and `lines` as CSV to `w`
+fn csv_to(w: &mut impl Write, header: &str, lines: impl Iterator<Item=String>) -> std::io::Result<()> {
+    writeln!(w, "{}", header)?;
+    for line in lines { writeln!(w, "{}", line)?; }
+    w.flush()
+}
+
+/// Write `rows` as JSON array
+fn write_json<T: Serialize>(path: &str, rows: &[T]) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    json_to(&mut BufWriter::new(file), rows).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// Write `header` and `lines` as CSV
+fn write_csv(path: &str, header: &str, lines: impl Iterator<Item=String>) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    csv_to(&mut BufWriter::new(file), header, lines).map_err(|e| format!("Failed to write {}: {}", path, e))
+}
+
+/// A column of a Parquet file
+enum Column {
+    Int64(Vec<i64>),
+    Double(Vec<f64>),
+    OptionalDouble(Vec<Option<f64>>),
+    Utf8(Vec<ByteArray>),
+}
+
+/// Write `columns` in a single row group to a Parquet file with `schema`
+fn write_parquet(path: &str, schema: &str, columns: Vec<Column>) -> Result<(), String> {
+    let err = |e: parquet::errors::ParquetError| format!("Failed to write {}: {}", path, e);
+    let schema = Arc::new(parse_message_type(schema).map_err(err)?);
+    let file = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+    let mut writer = SerializedFileWriter::new(file, schema, Arc::new(WriterProperties::builder().build())).map_err(err)?;
+    let mut row_group = writer.next_row_group().map_err(err)?;
+    for column in columns.iter() {
+        let mut col = match row_group.next_column().map_err(err)? {
+            Some(col) => col,
+            None => return Err(format!("Failed to write {}: too many columns", path))
+        };
+        match column {
+            Column::Int64(v) => col.typed::<Int64Type>().write_batch(v, None, None),
+            Column::Double(v) => col.typed::<DoubleType>().write_batch(v, None, None),
+            Column::OptionalDouble(v) => {
+                // the definition level is 0 for a null
+                let values: Vec<f64> = v.iter().flatten().cloned().collect();
+                let levels: Vec<i16> = v.iter().map(|x| x.is_some() as i16).collect();
+                col.typed::<DoubleType>().write_batch(&values, Some(&levels), None)
+            },
+            Column::Utf8(v) => col.typed::<ByteArrayType>().write_batch(v, None, None),
+        }.map_err(err)?;
+        col.close().map_err(err)?;
+    }
+    row_group.close().map_err(err)?;
+    writer.close().map_err(err)?;
+    Ok(())
+}
+
+/// Write klines to `path`
+pub fn write_klines(path: &str, format: Format, bars: &[Bar]) -> Result<(), String> {
+    let rows: Vec<KlineRow> = bars.iter().map(KlineRow::new).collect();
+    match format {
+        Format::Csv => write_csv(path, KLINES_HEADER, rows.iter().map(KlineRow::csv)),
+        Format::Json => write_json(path, &rows),
+        Format::Parquet => write_parquet(path, KLINES_SCHEMA, vec![
+            Column::Int64(rows.iter().map(|r| r.open_time as i64).collect()),
+            Column::Double(rows.iter().map(|r| r.open).collect()),
+            Column::Double(rows.iter().map(|r| r.high).collect()),
+            Column::Double(rows.iter().map(|r| r.low).collect()),
+            Column::Double(rows.iter().map(|r| r.close).collect()),
+            Column::Double(rows.iter().map(|r| r.volume).collect()),
+        ]),
+    }
+}
+
+/// Write klines to `w`, e.g. stdout. Parquet needs a file.
+pub fn print_klines(w: &mut impl Write, format: Format, bars: &[Bar]) -> Result<(), String> {
+    let rows: Vec<KlineRow> = bars.iter().map(KlineRow::new).collect();
+    match format {
+        Format::Csv => csv_to(w, KLINES_HEADER, rows.iter().map(KlineRow::csv)),
+        Format::Json => json_to(w, &rows),
+        Format::Parquet => return Err(String::from("Parquet can only be written to a file"))
+    }.map_err(|e| format!("Failed to write klines: {}", e))
+}
+
+/// Write a market snapshot to `path`
+pub fn write_snapshot(path: &str, format: Format, rows: &[SnapshotRow]) -> Result<(), String> {
+    let utf8 = |f: &dyn Fn(&SnapshotRow) -> &str| Column::Utf8(rows.iter().map(|r| ByteArray::from(f(r))).collect());
+    match format {
+        Format::Csv => write_csv(path, "symbol,base,quote,price,change_24h,volume", rows.iter().map(|r| {
+            let change = r.change_24h.map(|c| c.to_string()).unwrap_or_default();
+            format!("{},{},{},{},{},{}", r.symbol, r.base, r.quote, r.price, change, r.volume)
+        })),
+        Format::Json => write_json(path, rows),
+        Format::Parquet => write_parquet(path, SNAPSHOT_SCHEMA, vec![
+            utf8(&|r| &r.symbol),
+            utf8(&|r| &r.base),
+            utf8(&|r| &r.quote),
+            Column::Double(rows.iter().map(|r| r.price).collect()),
+            Column::OptionalDouble(rows.iter().map(|r| r.change_24h).collect()),
+            Column::Double(rows.iter().map(|r| r.volume).collect()),
+        ]),
+    }
+}
+
+#[test]
+fn test_export() -> Result<(), Box<dyn std::error::Error>> {
+    use parquet::file::reader::{FileReader, SerializedFileReader};
+    assert_eq!(Format::from_path("a/b.PARQUET")?, Format::Parquet);
+    assert!(Format::from_path("klines").is_err());
+    let bars = vec![Bar { t: 0, o: 1.0, h: 2.0, l: 0.5, c: 1.5, v: 10.0 }, Bar { t: 60000, o: 1.5, h: 1.5, l: 1.0, c: 1.0, v: 5.0 }];
+    let dir = std::env::temp_dir();
+    let path = |ext: &str| dir.join(format!("coinlive-export-{}.{}", std::process::id(), ext)).to_string_lossy().to_string();
+    write_klines(&path("csv"), Format::Csv, &bars)?;
+    assert_eq!(std::fs::read_to_string(path("csv"))?, "open_time,open,high,low,close,volume\n0,1,2,0.5,1.5,10\n60000,1.5,1.5,1,1,5\n");
+    let mut out: Vec<u8> = Vec::new();
+    print_klines(&mut out, Format::Csv, &bars[1..])?;
+    assert_eq!(String::from_utf8(out)?, "open_time,open,high,low,close,volume\n60000,1.5,1.5,1,1,5\n");
+    assert!(print_klines(&mut Vec::new(), Format::Parquet, &bars).is_err());
+    write_klines(&path("json"), Format::Json, &bars)?;
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path("json"))?)?;
+    assert_eq!(json[1]["open_time"], 60000);
+    let rows = vec![SnapshotRow { symbol: String::from("BTCUSDT"), base: String::from("BTC"), quote: String::from("USDT"),
+                                  price: 40000.0, change_24h: Some(1.5), volume: 1e9 },
+                    SnapshotRow { symbol: String::from("NEWUSDT"), base: String::from("NEW"), quote: String::from("USDT"),
+                                  price: 1.0, change_24h: None, volume: 0.0 }];
+    write_snapshot(&path("csv"), Format::Csv, &rows)?;
+    assert_eq!(std::fs::read_to_string(path("csv"))?,
+               "symbol,base,quote,price,change_24h,volume\nBTCUSDT,BTC,USDT,40000,1.5,1000000000\nNEWUSDT,NEW,USDT,1,,0\n");
+    write_snapshot(&path("parquet"), Format::Parquet, &rows)?;
+    let reader = SerializedFileReader::new(std::fs::File::open(path("parquet"))?)?;
+    assert_eq!(reader.metadata().file_metadata().num_rows(), 2);
+    assert_eq!(reader.metadata().file_metadata().schema_descr().num_columns(), 6);
+    for ext in ["csv", "json", "parquet"] { std::fs::remove_file(path(ext))?; }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! Websocket server that re-publishes the live price updates
+//!
+//! Clients subscribe to symbols with text messages like
+//!
+//! ```text
+//! {"subscribe":["BTCUSDT","ETH"]}
+//! {"unsubscribe":["ETH"]}
+//! ```
+//!
+//! and get the updates of these symbols as JSON arrays of `UpdateRow`, one array per message of the
+//! exchange feed. `*` subscribes to all symbols, a symbol without quote currency implies `USDT`.
+//! Every subscription is acknowledged with the current list of markets as `{"subscribed":[...]}`.
+//! Unknown symbols are rejected and a client can subscribe to at most `MAX_SUBSCRIPTIONS` markets.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::live::SharedLive;
+use crate::export::UpdateRow;
+use serde::{Serialize, Deserialize};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::{Receiver, error::RecvError};
+use tokio_tungstenite::{accept_async, tungstenite::Message};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Maximum number of markets a single client can subscribe to, `*` subscribes to all of them
+const MAX_SUBSCRIPTIONS: usize = 100;
+
+/// Subscription request of a client
+#[derive(Debug, Clone, Default, Deserialize)]
+struct Command {
+    #[serde(default)]
+    subscribe: Vec<String>,
+    #[serde(default)]
+    unsubscribe: Vec<String>,
+}
+
+/// Reply to a client
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum Reply {
+    Subscribed(Vec<String>),
+    Error(String),
+}
+
+/// Symbols a client subscribed to
+#[derive(Debug, Clone, Default)]
+struct Subscription {
+    symbols: BTreeSet<String>,  // market symbols, `*` for all
+}
+
+impl Subscription {
+    /// Apply the command `text`, returns the reply. `market` gives the market symbol of a
+    /// requested symbol, `None` if it is unknown.
+    fn apply(self: &mut Self, text: &str, market: &dyn Fn(&str) -> Option<String>) -> Reply {
+        let command: Command = match serde_json::from_str(text) {
+            Ok(command) => command,
+            Err(e) => return Reply::Error(format!("Bad command: {}", e))
+        };
+        let resolve = |symbol: &String| if symbol == "*" { Some(symbol.clone()) } else { market(symbol) };
+        for symbol in command.unsubscribe.iter() {
+            self.symbols.remove(&resolve(symbol).unwrap_or_else(|| symbol.to_uppercase()));
+        }
+        let mut unknown: Vec<&str> = Vec::new();
+        for symbol in command.subscribe.iter() {
+            match resolve(symbol) {
+                Some(symbol) if self.symbols.contains(&symbol) => {},
+                Some(_) if self.symbols.len() >= MAX_SUBSCRIPTIONS => {
+                    return Reply::Error(format!("At most {} subscriptions, use \"*\" for all", MAX_SUBSCRIPTIONS));
+                },
+                Some(symbol) => { self.symbols.insert(symbol); },
+                None => unknown.push(symbol)
+            }
+        }
+        if !unknown.is_empty() {
+            return Reply::Error(format!("Unknown symbols: {}", unknown.join(", ")));
+        }
+        Reply::Subscribed(self.symbols.iter().cloned().collect())
+    }
+    /// Is the market `symbol` subscribed?
+    fn matches(self: &Self, symbol: &str) -> bool {
+        self.symbols.contains("*") || self.symbols.contains(symbol)
+    }
+}
+
+/// Serve a single client until it disconnects
+async fn client(stream: TcpStream, live: SharedLive, mut feed: Receiver<Arc<Vec<Update>>>) -> Result<(), String> {
+    let market = |symbol: &str| live.lock().ok().and_then(|l| l.info(symbol).map(|i| i.symbol.to_string()));
+    let ws = accept_async(stream).await.map_err(|e| format!("Websocket handshake failed: {}", e))?;
+    let (mut write, mut read) = ws.split();
+    let mut subscription = Subscription::default();
+    loop {
+        let out = tokio::select! {
+            msg = read.next() => match msg {
+                Some(Ok(Message::Text(text))) => Some(serde_json::to_string(&subscription.apply(&text, &market))),
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => None,
+                Some(Err(e)) => return Err(format!("Websocket error: {}", e))
+            },
+            updates = feed.recv() => match updates {
+                Ok(updates) => {
+                    let rows: Vec<UpdateRow> = updates.iter().filter(|u| subscription.matches(&u.symbol)).map(UpdateRow::new).collect();
+                    if rows.is_empty() { None } else { Some(serde_json::to_string(&rows)) }
+                },
+                // a slow client misses updates, but keeps the ones that follow
+                Err(RecvError::Lagged(n)) => Some(serde_json::to_string(&Reply::Error(format!("Too slow, skipped {} messages", n)))),
+                Err(RecvError::Closed) => return Ok(())
+            }
+        };
+        if let Some(out) = out {
+            let text = out.map_err(|e| e.to_string())?;
+            write.send(Message::Text(text)).await.map_err(|e| format!("Websocket error: {}", e))?;
+        }
+    }
+}
+
+/// Accept websocket clients on `listener` and send them the updates of `live`, forever
+pub async fn serve(listener: TcpListener, live: SharedLive) -> Result<(), String> {
+    loop {
+        let (stream, _) = listener.accept().await.map_err(|e| format!("Failed to accept connection: {}", e))?;
+        let feed = live.lock().map_err(|_| String::from("Live state unavailable"))?.subscribe();
+        // a failed connection concerns only its client
+        tokio::spawn(client(stream, live.clone(), feed));
+    }
+}
+
+#[tokio::test]
+async fn test_fanout() -> Result<(), Box<dyn std::error::Error>> {
+    use tokio_tungstenite::connect_async;
+    let live = crate::live::Live::shared();
+    let listener = crate::http::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(serve(listener, live.clone()));
+    if let Ok(mut live) = live.lock() {
+        live.set_infos(&[mock_info("BTC", "USDT", "1"), mock_info("ETH", "USDT", "1"), mock_info("ETH", "BTC", "1")]);
+    }
+    let (mut ws, _) = connect_async(format!("ws://{}/", addr)).await?;
+    ws.send(Message::Text(String::from("{\"subscribe\":[\"eth\",\"ETHBTC\"]}"))).await?;
+    assert_eq!(ws.next().await.ok_or("closed")??, Message::Text(String::from("{\"subscribed\":[\"ETHBTC\",\"ETHUSDT\"]}")));
+    ws.send(Message::Text(String::from("{\"subscribe\":[\"NOSUCHCOIN\"]}"))).await?;
+    assert_eq!(ws.next().await.ok_or("closed")??, Message::Text(String::from("{\"error\":\"Unknown symbols: NOSUCHCOIN\"}")));
+    if let Ok(mut live) = live.lock() {
+        live.update(2, &[mock_update("BTCUSDT", 1, "2", "1")]);
+        live.update(3, &[mock_update("BTCUSDT", 1, "2", "1"), mock_update("ETHUSDT", 1, "2", "1")]);
+    }
+    let text = ws.next().await.ok_or("closed")??.into_text()?;
+    assert_eq!(text, "[{\"symbol\":\"ETHUSDT\",\"ts\":1,\"price\":2.0,\"price_24h\":1.0,\"change_24h\":100.0}]");
+    ws.send(Message::Text(String::from("{\"subscribe\":"))).await?;
+    assert!(ws.next().await.ok_or("closed")??.into_text()?.starts_with("{\"error\":\"Bad command"));
+    Ok(())
+}
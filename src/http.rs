@@ -0,0 +1,157 @@
+//! Minimal HTTP/1.1 server for the local endpoints
+//!
+//! Only `GET` requests without body are supported, every connection serves a single request. The
+//! request head has to arrive within `REQUEST_TIMEOUT_MILLIS` and fit in `MAX_REQUEST_BYTES`.
+//! Handlers run on the blocking thread pool, so they may wait for downloads.
+#![allow(dead_code)]
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+
+/// Maximum size of the request head
+const MAX_REQUEST_BYTES: usize = 8192;
+
+/// Time a client has to send the request head
+const REQUEST_TIMEOUT_MILLIS: u64 = 5000;
+
+/// A request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,       // without query
+    pub query: String,      // after `?`, may be empty
+}
+
+impl Request {
+    /// Parse the request line of `head`, e.g. `GET /metrics HTTP/1.1`
+    pub fn parse(head: &str) -> Option<Request> {
+        let mut it = head.lines().next()?.split_whitespace();
+        let method = it.next()?;
+        let target = it.next()?;
+        if !it.next()?.starts_with("HTTP/") { return None; }
+        let (path, query) = target.split_once('?').unwrap_or((target, ""));
+        Some(Request { method: String::from(method), path: String::from(path), query: String::from(query) })
+    }
+    /// Value of the query parameter `name`
+    pub fn param(self: &Self, name: &str) -> Option<&str> {
+        self.query.split('&').filter_map(|kv| kv.split_once('=')).find(|(k, _)| *k == name).map(|(_, v)| v)
+    }
+}
+
+/// A response
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    pub fn ok(content_type: &'static str, body: String) -> Self {
+        Response { status: 200, content_type: content_type, body: body }
+    }
+    pub fn error(status: u16, body: &str) -> Self {
+        Response { status: status, content_type: "text/plain; charset=utf-8", body: format!("{}\n", body) }
+    }
+    fn reason(self: &Self) -> &str {
+        match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            408 => "Request Timeout",
+            431 => "Request Header Fields Too Large",
+            502 => "Bad Gateway",
+            _ => "Internal Server Error"
+        }
+    }
+    /// Status line, headers and body
+    fn bytes(self: &Self) -> Vec<u8> {
+        format!("HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                self.status, self.reason(), self.content_type, self.body.len(), self.body).into_bytes()
+    }
+}
+
+/// Request handler
+pub type Handler = Arc<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// Listen on `addr`, e.g. `127.0.0.1:9100`
+pub async fn bind(addr: &str) -> Result<TcpListener, String> {
+    TcpListener::bind(addr).await.map_err(|e| format!("Failed to listen on {}: {}", addr, e))
+}
+
+/// Read the request head of `stream`, `None` if the client closed the connection
+async fn read_head(stream: &mut TcpStream) -> std::io::Result<Option<Vec<u8>>> {
+    let mut head: Vec<u8> = Vec::with_capacity(1024);
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") && head.len() <= MAX_REQUEST_BYTES {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 { return Ok(None); }
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(Some(head))
+}
+
+/// Response of `handler` to the request `head`
+async fn respond(head: &[u8], handler: Handler) -> Response {
+    if head.len() > MAX_REQUEST_BYTES {
+        return Response::error(431, "Request head too large");
+    }
+    match Request::parse(&String::from_utf8_lossy(head)) {
+        Some(request) if request.method == "GET" => {
+            tokio::task::spawn_blocking(move || handler(&request)).await
+                .unwrap_or_else(|_| Response::error(500, "Handler failed"))
+        },
+        Some(_) => Response::error(405, "Only GET is supported"),
+        None => Response::error(400, "Bad request")
+    }
+}
+
+/// Read the request of `stream` and write the response of `handler`
+async fn handle(mut stream: TcpStream, handler: Handler) -> std::io::Result<()> {
+    // slow or idle clients must not hold the connection
+    let timeout = std::time::Duration::from_millis(REQUEST_TIMEOUT_MILLIS);
+    let response = match tokio::time::timeout(timeout, read_head(&mut stream)).await {
+        Ok(Ok(Some(head))) => respond(&head, handler).await,
+        Ok(Ok(None)) => return Ok(()),
+        Ok(Err(e)) => return Err(e),
+        Err(_) => Response::error(408, "Request timeout")
+    };
+    stream.write_all(&response.bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Serve the requests on `listener` with `handler`, forever
+pub async fn serve(listener: TcpListener, handler: Handler) -> Result<(), String> {
+    loop {
+        let (stream, _) = listener.accept().await.map_err(|e| format!("Failed to accept connection: {}", e))?;
+        // a failed connection concerns only its client
+        tokio::spawn(handle(stream, handler.clone()));
+    }
+}
+
+#[tokio::test]
+async fn test_http() -> Result<(), Box<dyn std::error::Error>> {
+    let request = Request::parse("GET /klines/BTCUSDT?interval=1h&x HTTP/1.1\r\nHost: a\r\n\r\n").ok_or("no request")?;
+    assert_eq!(request.path, "/klines/BTCUSDT");
+    assert_eq!(request.param("interval"), Some("1h"));
+    assert_eq!(request.param("x"), None);
+    assert_eq!(Request::parse("GET /"), None);
+    let listener = bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let handler: Handler = Arc::new(|r: &Request| Response::ok("text/plain", format!("path {}", r.path)));
+    tokio::spawn(serve(listener, handler));
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"), "{}", response);
+    assert!(response.ends_with("\r\n\r\npath /metrics"), "{}", response);
+    let mut stream = TcpStream::connect(addr).await?;
+    stream.write_all(&vec![b'a'; 10_000]).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    assert!(response.starts_with("HTTP/1.1 431 "), "{}", response);
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! Live market state shared with the HTTP endpoints
+//!
+//! The consumer of the websocket feed, the `UI` or `coinlive serve`, keeps the latest update of every
+//! symbol and the symbol infos in `Live`. The endpoints `/metrics` and `/prices` read them, the
+//! websocket fan-out subscribes to the updates.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::export::SnapshotRow;
+use dec::Decimal64;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Number of feed messages buffered for a slow subscriber
+const FEED_CAPACITY: usize = 256;
+
+/// Latest update of a symbol
+#[derive(Debug, Clone, PartialEq)]
+pub struct Latest {
+    pub px: Decimal64,
+    pub px_24h: Decimal64,  // price 24h ago
+    pub ts: u64,            // exchange timestamp (millis)
+    pub ts_rec: u64,        // receive timestamp (millis)
+}
+
+impl Latest {
+    /// Price change over 24h in percent
+    pub fn change_24h(self: &Self) -> f64 {
+        (dec_to_f64(self.px)/dec_to_f64(self.px_24h)-1.0)*100.0
+    }
+}
+
+/// Latest updates and infos of all symbols
+#[derive(Debug, Clone, Default)]
+pub struct Live {
+    pub latest: HashMap<Symbol, Latest>,
+    pub infos: Vec<Info>,   // sorted by volume
+    pub messages: u64,      // number of websocket messages
+    feed: Option<broadcast::Sender<Arc<Vec<Update>>>>, // updates for the subscribers
+}
+
+/// `Live` shared between tasks
+pub type SharedLive = Arc<Mutex<Live>>;
+
+impl Live {
+    pub fn shared() -> SharedLive {
+        Arc::new(Mutex::new(Live::default()))
+    }
+    /// Add the `updates` of a websocket message received at `ts_rec`
+    pub fn update(self: &mut Self, ts_rec: u64, updates: &[Update]) {
+        self.messages += 1;
+        for u in updates.iter() {
+            self.latest.insert(u.symbol.clone(), Latest { px: u.px, px_24h: u.px_24h, ts: u.ts, ts_rec: ts_rec });
+        }
+        if let Some(feed) = &self.feed {
+            if feed.receiver_count() > 0 {
+                let _ = feed.send(Arc::new(updates.to_vec()));
+            }
+        }
+    }
+    /// Receive the updates of every following websocket message
+    pub fn subscribe(self: &mut Self) -> broadcast::Receiver<Arc<Vec<Update>>> {
+        self.feed.get_or_insert_with(|| broadcast::channel(FEED_CAPACITY).0).subscribe()
+    }
+    /// Replace the infos
+    pub fn set_infos(self: &mut Self, infos: &[Info]) {
+        self.infos = sort_infos(infos.to_vec());
+    }
+    /// Info of the market `symbol`, case insensitive. A symbol without quote currency implies `USDT`.
+    pub fn info(self: &Self, symbol: &str) -> Option<&Info> {
+        let upper = symbol.to_uppercase();
+        let usdt = format!("{}USDT", upper);
+        self.infos.iter().find(|i| i.symbol == upper.as_str()).or_else(|| self.infos.iter().find(|i| i.symbol == usdt.as_str()))
+    }
+    /// Price, 24h change and volume of the market `info`
+    pub fn row(self: &Self, info: &Info) -> Option<SnapshotRow> {
+        self.latest.get(&info.symbol).map(|l| SnapshotRow {
+            symbol: info.symbol.to_string(), base: info.base.to_string(), quote: info.quote.to_string(),
+            price: dec_to_f64(l.px), change_24h: Some(l.change_24h()).filter(|c| c.is_finite()), volume: dec_to_f64(info.volume)
+        })
+    }
+    /// Prices of all markets with an update, by volume
+    pub fn rows(self: &Self) -> Vec<SnapshotRow> {
+        self.infos.iter().filter_map(|info| self.row(info)).collect()
+    }
+}
+
+#[test]
+fn test_live() {
+    let mut live = Live::default();
+    live.set_infos(&[mock_info("ETH", "BTC", "10"), mock_info("BTC", "USDT", "20")]);
+    live.update(1000, &[mock_update("BTCUSDT", 900, "110", "100")]);
+    assert_eq!(live.info("btc").map(|i| i.symbol.clone()), Some(mock_symbol("BTCUSDT")));
+    assert_eq!(live.info("ethbtc").map(|i| i.symbol.clone()), Some(mock_symbol("ETHBTC")));
+    assert!(live.info("ETH").is_none());
+    let rows = live.rows();
+    assert_eq!(rows.len(), 1);
+    assert_eq!((rows[0].symbol.as_str(), rows[0].price, rows[0].volume), ("BTCUSDT", 110.0, 20.0));
+}
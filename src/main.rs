@@ -1,13 +1,34 @@
 
 mod utils;
 mod ui;
+mod stream;
+mod book;
+mod trades;
+mod alerts;
+mod portfolio;
+mod rates;
+mod arbitrage;
+mod analytics;
+mod record;
+mod replay;
+mod cache;
+mod export;
+mod cli;
+mod http;
+mod live;
+mod metrics;
+mod api;
+mod fanout;
 use crate::{
     utils::*,
     ui::*
 };
 use std::{
     io,
-    time::Duration
+    fs,
+    time::Duration,
+    sync::Arc,
+    sync::atomic::{AtomicBool, Ordering}
 };
 use termion::{
     event::Key,
@@ -15,27 +36,39 @@ use termion::{
     raw::IntoRawMode
 };
 use tui::{Terminal, backend::TermionBackend};
-use tokio_tungstenite::{connect_async};
 use tokio::sync::mpsc::UnboundedSender;
-use futures_util::{future, StreamExt};
-use url::Url;
-use clap::{Command};
+use futures_util::future;
+use clap::{Command, Arg, ArgAction, ArgGroup};
 use version::version;
 
 /// Duration of `sleep` in `listen_keys` loop
 const LISTEN_KEYS_SLEEP_MILLIS: u64 = 100;
 
-/// Binance 24h ticker stream endpoint
-const URI_WS_TICKER: &str = "wss://stream.binance.com:9443/ws/!ticker@arr";
-
 /// Listen to terminal input.
 /// 
 /// This is simply an endless loop that reads the terminal input in `LOOP_SPEED` intervals and sends
-/// the appropriate message to `tx`.
-async fn listen_keys(tx: UnboundedSender<Msg>) -> Result<(), String> {
+/// the appropriate message to `tx`. While `input_mode` is set the keys are sent as `Msg::Input`.
+async fn listen_keys(tx: UnboundedSender<Msg>, input_mode: Arc<AtomicBool>) -> Result<(), String> {
     let mut stdin = termion::async_stdin().keys();
     loop {
         if let Some(Ok(key)) = stdin.next() {
+            if input_mode.load(Ordering::Relaxed) {
+                match key {
+                    Key::Ctrl('c') => {
+                        tx.send(Msg::Stop).expect("UI failed");
+                        break;
+                    },
+                    Key::Char('\n') => { tx.send(Msg::Enter).expect("UI failed"); },
+                    Key::Char(c)     => { tx.send(Msg::Input(c)).expect("UI failed"); },
+                    Key::Backspace   => { tx.send(Msg::Backspace).expect("UI failed"); },
+                    Key::Esc         => { tx.send(Msg::Esc).expect("UI failed"); },
+                    Key::Up          => { tx.send(Msg::ArrowUp).expect("UI failed"); },
+                    Key::Down        => { tx.send(Msg::ArrowDown).expect("UI failed"); },
+                    _ => {}
+                }
+                tokio::time::sleep(Duration::from_millis(LISTEN_KEYS_SLEEP_MILLIS)).await;
+                continue;
+            }
             match key {
                 Key::Char('q') => {
                     tx.send(Msg::Stop).expect("UI failed");
@@ -49,6 +82,22 @@ async fn listen_keys(tx: UnboundedSender<Msg>) -> Result<(), String> {
                 Key::Char('t')  => { tx.send(Msg::PriceTable).expect("UI failed"); },
                 Key::Char('%')  => { tx.send(Msg::TogglePercent).expect("UI failed"); },
                 Key::Char('x')  => { tx.send(Msg::ToggleExtended).expect("UI failed"); },
+                Key::Char('S')  => { tx.send(Msg::ToggleSparkline).expect("UI failed"); },
+                Key::Char('b')  => { tx.send(Msg::ToggleBook).expect("UI failed"); },
+                Key::Char('o')  => { tx.send(Msg::OrderBook).expect("UI failed"); },
+                Key::Char('d')  => { tx.send(Msg::DepthChart).expect("UI failed"); },
+                Key::Char('r')  => { tx.send(Msg::Trades).expect("UI failed"); },
+                Key::Char('A')  => { tx.send(Msg::Alerts).expect("UI failed"); },
+                Key::Char('p')  => { tx.send(Msg::Portfolio).expect("UI failed"); },
+                Key::Char('c')  => { tx.send(Msg::Converter).expect("UI failed"); },
+                Key::Char('T')  => { tx.send(Msg::Arbitrage).expect("UI failed"); },
+                Key::Char('m')  => { tx.send(Msg::Analytics).expect("UI failed"); },
+                Key::Char('H')  => { tx.send(Msg::Heatmap).expect("UI failed"); },
+                Key::Char('e')  => { tx.send(Msg::Export).expect("UI failed"); },
+                Key::Char(' ')  => { tx.send(Msg::Pause).expect("UI failed"); },
+                Key::Char('.')  => { tx.send(Msg::Step).expect("UI failed"); },
+                Key::Char('n')  => { tx.send(Msg::NewAlert).expect("UI failed"); },
+                Key::Char('B')  => { tx.send(Msg::ToggleBell).expect("UI failed"); },
                 Key::Char('s')  => { tx.send(Msg::Search).expect("UI failed"); },
                 Key::Char('h')  => { tx.send(Msg::Help).expect("UI failed"); },
                 Key::Char('a')  => { tx.send(Msg::About).expect("UI failed"); },
@@ -70,6 +119,7 @@ async fn listen_keys(tx: UnboundedSender<Msg>) -> Result<(), String> {
                 Key::Home       => { tx.send(Msg::Home).expect("UI failed"); },
                 Key::Char('\n') => { tx.send(Msg::Enter).expect("UI failed"); },
                 Key::Esc        => { tx.send(Msg::Esc).expect("UI failed"); },
+                Key::Delete     => { tx.send(Msg::Delete).expect("UI failed"); },
                 key => { 
                     tx.send(Msg::Msg(format!("Unknown command {:?}", key)))
                       .map_err(|e| format!("UI failed: {:?}", e))?; 
@@ -81,51 +131,6 @@ async fn listen_keys(tx: UnboundedSender<Msg>) -> Result<(), String> {
     Ok(())
 }
 
-/// Websocket stream
-async fn ws(uri: &str, ui_tx: UnboundedSender<Msg>) -> Result<(), String> {
-    let uri: Url = Url::parse(uri).map_err(|e| format!("Bad url: {:?}", e))?;
-    let (ws_stream, response) = match connect_async(uri).await {
-        Ok((ws_stream, response)) => { (ws_stream, response) },
-        Err(e) => { 
-            ui_tx.send(Msg::Msg(format!("Error connecting: {:?}", e)))
-                 .map_err(|e| format!("UI failed: {:?}", e))?;
-            return Ok(());
-        }
-    };
-    ui_tx.send(Msg::Msg(format!("Websocket connected:\n{:?}", response)))
-         .map_err(|e| format!("UI failed: {:?}", e))?;
-
-    let (_, mut read) = ws_stream.split();
-
-    ui_tx.send(Msg::Msg(String::from("Starting..."))).expect("UI failed");
-    loop {
-        let next = read.next().await;
-        let now = now_timestamp();
-        match next {
-            Some(msg) => {
-                match msg {
-                    Ok(msg)  => {
-                        let msg = msg.to_string();
-                        ui_tx.send(Msg::WS(now, msg))
-                             .map_err(|e| format!("UI failed: {:?}", e))?;
-                    }, 
-                    Err(e) => {
-                        ui_tx.send(Msg::Msg(format!("Error: {:?}", e)))
-                             .map_err(|e| format!("UI failed: {:?}", e))?;
-                        return Err(format!("Websocket error: {:?}", e));
-                    }
-                }
-            },
-            None => {
-                ui_tx.send(Msg::Msg(String::from("Stream end")))
-                     .map_err(|e| format!("UI failed: {:?}", e))?;
-                break;
-            }
-        }
-    }
-    Ok(())
-}
-
 /// Essentially calls `get_infos`, sorts the `Info` vector and sends the `Msg`s.
 async fn get_symbols_async(tx: UnboundedSender<Msg>) -> Result<(), String> {
     tx.send(Msg::Msg(String::from("Getting symbols..."))).map_err(|e| format!("UI failed: {:?}", e))?;
@@ -140,28 +145,386 @@ async fn get_symbols_async(tx: UnboundedSender<Msg>) -> Result<(), String> {
     Ok(())
 }
 
+/// Argument `--output FILE` of the export subcommands
+fn export_output_arg() -> Arg {
+    Arg::new("output")
+        .long("output")
+        .short('o')
+        .value_name("FILE")
+        .required(true)
+        .help("Write to FILE, the format is taken from the extension unless --format is given")
+}
+
+/// Argument `--format FORMAT` of the export subcommands
+fn export_format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .short('f')
+        .value_name("FORMAT")
+        .value_parser(["csv", "json", "parquet"])
+        .help("File format")
+}
+
+/// Argument `--metrics ADDR` of the UI and the serve subcommand
+fn metrics_arg() -> Arg {
+    Arg::new("metrics")
+        .long("metrics")
+        .value_name("ADDR")
+        .help("Serve Prometheus metrics of the live prices on http://ADDR/metrics, e.g. 127.0.0.1:9100")
+}
+
+/// Argument `--api ADDR` of the UI and the serve subcommand
+fn api_arg() -> Arg {
+    Arg::new("api")
+        .long("api")
+        .value_name("ADDR")
+        .help("Serve the live prices and klines as JSON on http://ADDR/, e.g. 127.0.0.1:8080")
+}
+
+/// Argument `--ws-server ADDR` of the UI and the serve subcommand
+fn ws_server_arg() -> Arg {
+    Arg::new("ws-server")
+        .long("ws-server")
+        .value_name("ADDR")
+        .help("Re-publish the live price updates to websocket clients on ws://ADDR/, e.g. 127.0.0.1:8765")
+}
+
 /// The main function
 #[tokio::main]
 async fn main() -> Result<(),Box<dyn std::error::Error>> {
 
-    let _matches = Command::new("coinlive")
+    let matches = Command::new("coinlive")
         .about("Live cryptocurrency prices CLI")
         .version(version!())
         .author("Mayer Analytics. https://github.com/mayeranalytics/coinlive")
+        .arg(Arg::new("alerts")
+            .long("alerts")
+            .value_name("FILE")
+            .help("Load alert rules from FILE, one rule per line, e.g. 'BTC above 70000'"))
+        .arg(Arg::new("bell")
+            .long("bell")
+            .action(ArgAction::SetTrue)
+            .help("Ring the terminal bell when an alert is triggered"))
+        .arg(Arg::new("alert-command")
+            .long("alert-command")
+            .value_name("CMD")
+            .help("Run shell command CMD when an alert is triggered, with the alert in the environment \
+                   variables COINLIVE_SYMBOL, COINLIVE_PRICE, COINLIVE_RULE and COINLIVE_TS"))
+        .arg(Arg::new("alert-command-interval")
+            .long("alert-command-interval")
+            .value_name("SECONDS")
+            .value_parser(clap::value_parser!(u64))
+            .help("Run the alert command at most every SECONDS seconds [default: 10]"))
+        .arg(Arg::new("portfolio")
+            .long("portfolio")
+            .value_name("FILE")
+            .help("Load holdings from FILE, one 'ASSET QUANTITY COST' per line, e.g. 'BTC 0.5 30000'"))
+        .arg(Arg::new("fee")
+            .long("fee")
+            .value_name("PERCENT")
+            .value_parser(clap::value_parser!(f64))
+            .help("Fee per trade in percent for the triangular arbitrage page [default: 0.1]"))
+        .arg(Arg::new("record")
+            .long("record")
+            .value_name("FILE")
+            .help("Record the websocket feed to FILE (gzip compressed, one JSON object per line)"))
+        .arg(Arg::new("replay")
+            .long("replay")
+            .value_name("FILE")
+            .conflicts_with("record")
+            .help("Replay the feed recorded with --record from FILE instead of connecting to the exchange"))
+        .arg(Arg::new("speed")
+            .long("speed")
+            .value_name("N")
+            .requires("replay")
+            .value_parser(clap::value_parser!(f64))
+            .help("Replay N times faster than recorded [default: 1]"))
+        .arg(Arg::new("cache-dir")
+            .long("cache-dir")
+            .value_name("DIR")
+            .global(true)
+            .help("Cache klines in DIR [default: $XDG_CACHE_HOME/coinlive or ~/.cache/coinlive]"))
+        .arg(Arg::new("no-cache")
+            .long("no-cache")
+            .action(ArgAction::SetTrue)
+            .global(true)
+            .conflicts_with("cache-dir")
+            .help("Don't cache klines on disk"))
+        .arg(metrics_arg())
+        .arg(api_arg())
+        .arg(ws_server_arg())
+        .subcommand(Command::new("export")
+            .about("Export klines or a snapshot of all prices to CSV, JSON or Parquet")
+            .subcommand_required(true)
+            .subcommand(Command::new("klines")
+                .about("Export the last 1000 klines of a symbol")
+                .arg(Arg::new("symbol")
+                    .required(true)
+                    .value_name("SYMBOL")
+                    .help("Symbol, e.g. BTCUSDT"))
+                .arg(Arg::new("interval")
+                    .long("interval")
+                    .short('i')
+                    .value_name("INTERVAL")
+                    .default_value("1m")
+                    .help("Kline interval: 1m 3m 5m 15m 30m 1h 2h 4h 6h 8h 12h 1d 3d 1w 1M"))
+                .arg(export_output_arg())
+                .arg(export_format_arg()))
+            .subcommand(Command::new("snapshot")
+                .about("Export price, 24h change and volume of all symbols")
+                .arg(export_output_arg())
+                .arg(export_format_arg())))
+        .subcommand(Command::new("price")
+            .about("Print the current prices and exit")
+            .arg(Arg::new("symbols")
+                .required(true)
+                .num_args(1..)
+                .value_name("SYMBOL")
+                .help("Asset (priced in the --quote currency), BASE/QUOTE pair or market symbol, e.g. BTC ETH/BTC BNBUSDT"))
+            .arg(Arg::new("quote")
+                .long("quote")
+                .short('q')
+                .value_name("ASSET")
+                .default_value("USDT")
+                .help("Quote currency for symbols given without one"))
+            .arg(Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("format")
+                .help("Print JSON"))
+            .arg(Arg::new("format")
+                .long("format")
+                .value_name("TEMPLATE")
+                .help("Print each price with TEMPLATE, placeholders {symbol} {base} {quote} {price} {change} \
+                       [default: '{base}/{quote} {price} {change}%']")))
+        .subcommand(Command::new("klines")
+            .about("Download klines of a symbol")
+            .arg(Arg::new("symbol")
+                .required(true)
+                .value_name("SYMBOL")
+                .help("Market symbol, e.g. BTCUSDT"))
+            .arg(Arg::new("interval")
+                .long("interval")
+                .short('i')
+                .value_name("INTERVAL")
+                .default_value("1m")
+                .help("Kline interval, one of 1m 3m 5m 15m 30m 1h 2h 4h 6h 8h 12h 1d 3d 1w 1M"))
+            .arg(Arg::new("from")
+                .long("from")
+                .value_name("TIME")
+                .help("First bar, UTC time like 2024-01-01 or '2024-01-01 12:00' [default: 1000 bars before --to]"))
+            .arg(Arg::new("to")
+                .long("to")
+                .value_name("TIME")
+                .help("Only bars opened before this UTC time [default: now]"))
+            .arg(Arg::new("output")
+                .long("output")
+                .short('o')
+                .value_name("FILE")
+                .help("Output file [default: stdout]"))
+            .arg(export_format_arg()))
+        .subcommand(Command::new("chart")
+            .about("Print the price chart of a symbol and exit")
+            .arg(Arg::new("symbol")
+                .required(true)
+                .value_name("SYMBOL")
+                .help("Market symbol, e.g. BTCUSDT"))
+            .arg(Arg::new("interval")
+                .long("interval")
+                .short('i')
+                .value_name("INTERVAL")
+                .default_value("1h")
+                .help("Kline interval, one of 1m 3m 5m 15m 30m 1h 2h 4h 6h 8h 12h 1d 3d 1w 1M"))
+            .arg(Arg::new("width")
+                .long("width")
+                .value_name("COLUMNS")
+                .value_parser(clap::value_parser!(u16).range(20..=1000))
+                .default_value("100")
+                .help("Width of the chart"))
+            .arg(Arg::new("height")
+                .long("height")
+                .value_name("ROWS")
+                .value_parser(clap::value_parser!(u16).range(5..=500))
+                .default_value("30")
+                .help("Height of the chart"))
+            .arg(Arg::new("no-color")
+                .long("no-color")
+                .action(ArgAction::SetTrue)
+                .help("Plain text without ANSI colors")))
+        .subcommand(Command::new("serve")
+            .about("Serve the live prices without the terminal UI")
+            .arg(metrics_arg())
+            .arg(api_arg())
+            .arg(ws_server_arg())
+            .group(ArgGroup::new("endpoints").args(["metrics", "api", "ws-server"]).multiple(true).required(true)))
+        .subcommand(Command::new("status")
+            .about("Keep a status line of the prices up to date, for tmux, polybar or i3bar")
+            .arg(Arg::new("symbols")
+                .long("symbols")
+                .short('s')
+                .num_args(1..)
+                .value_delimiter(',')
+                .value_name("SYMBOL")
+                .default_value("BTC,ETH")
+                .help("Symbols to show, a symbol without quote currency implies USDT"))
+            .arg(Arg::new("format")
+                .long("format")
+                .value_name("TEMPLATE")
+                .default_value("{sym} {px} {pct}")
+                .help("Template of a single symbol, placeholders {sym} {symbol} {px} {pct}"))
+            .arg(Arg::new("separator")
+                .long("separator")
+                .value_name("TEXT")
+                .default_value("  ")
+                .help("Text between the symbols"))
+            .arg(Arg::new("output")
+                .long("output")
+                .short('o')
+                .value_name("OUTPUT")
+                .value_parser(["ansi", "plain", "tmux", "polybar", "i3bar"])
+                .default_value("ansi")
+                .help("ansi rewrites a single line in the terminal, plain, tmux and polybar print a line per change, \
+                       i3bar prints the i3bar JSON protocol")))
+        .subcommand(Command::new("stream")
+            .about("Print the live price updates to stdout, one per line")
+            .arg(Arg::new("symbols")
+                .long("symbols")
+                .short('s')
+                .num_args(1..)
+                .value_delimiter(',')
+                .value_name("SYMBOL")
+                .help("Only updates of these symbols, e.g. BTC,ETHBTC [default: all]"))
+            .arg(Arg::new("format")
+                .long("format")
+                .short('f')
+                .value_name("FORMAT")
+                .value_parser(["ndjson", "csv"])
+                .default_value("ndjson")
+                .help("Output format")))
         .get_matches();
 
+    match matches.subcommand() {
+        Some(("export", m)) => return cli::export(m).await,
+        Some(("price", m)) => return cli::price(m).await,
+        Some(("klines", m)) => return cli::klines(m).await,
+        Some(("chart", m)) => return cli::chart(m).await,
+        Some(("serve", m)) => return cli::serve(m).await,
+        Some(("status", m)) => return cli::status(m).await,
+        Some(("stream", m)) => return cli::stream(m).await,
+        _ => {}
+    }
+
+    // read the alert rules before the terminal is switched to raw mode, so errors are readable
+    let alert_rules: Vec<String> = match matches.get_one::<String>("alerts") {
+        Some(file) => fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read alerts file {}: {}", file, e))?
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(String::from)
+            .collect(),
+        None => Vec::new()
+    };
+    for rule in alert_rules.iter() {
+        alerts::Rule::parse(rule)?;
+    }
+    let portfolio = match matches.get_one::<String>("portfolio") {
+        Some(file) => {
+            let text = fs::read_to_string(file).map_err(|e| format!("Failed to read portfolio file {}: {}", file, e))?;
+            Some(portfolio::Portfolio::parse(&text)?)
+        },
+        None => None
+    };
+
+    let recorder = match matches.get_one::<String>("record") {
+        Some(file) => Some(record::Recorder::create(file)?),
+        None => None
+    };
+
+    let speed = matches.get_one::<f64>("speed").cloned().unwrap_or(1.0);
+    if !(speed > 0.0 && speed.is_finite()) {
+        return Err(format!("Invalid replay speed {}", speed).into());
+    }
+    let replay = matches.get_one::<String>("replay").cloned();
+    if let Some(file) = &replay {
+        fs::metadata(file).map_err(|e| format!("Failed to open replay file {}: {}", file, e))?;
+    }
+
+    // listen before the terminal is taken over, so that a busy address is reported
+    let metrics_listener = match matches.get_one::<String>("metrics") {
+        Some(addr) => Some(http::bind(addr).await?),
+        None => None
+    };
+    let api_listener = match matches.get_one::<String>("api") {
+        Some(addr) => Some(http::bind(addr).await?),
+        None => None
+    };
+    let ws_listener = match matches.get_one::<String>("ws-server") {
+        Some(addr) => Some(http::bind(addr).await?),
+        None => None
+    };
+
     // terminal raw mode to allow reading stdin one key at a time
     let stdout = io::stdout().into_raw_mode().unwrap();
     let backend = TermionBackend::new(stdout);
     let terminal = Terminal::new(backend)?;
     let ui = UI::new(terminal);
 
-    tokio::spawn(get_symbols_async(ui.tx.clone()));
+    for rule in alert_rules.into_iter() {
+        ui.tx.send(Msg::AddAlert(rule))?;
+    }
+    if matches.get_flag("bell") {
+        ui.tx.send(Msg::ToggleBell)?;
+    }
+    if let Some(portfolio) = portfolio {
+        ui.tx.send(Msg::SetPortfolio(portfolio))?;
+    }
+    if let Some(recorder) = recorder {
+        ui.tx.send(Msg::Record(recorder))?;
+    }
+    if let Some(cache) = cli::kline_cache(&matches) {
+        ui.tx.send(Msg::KlineCache(cache))?;
+    }
+    if metrics_listener.is_some() || api_listener.is_some() || ws_listener.is_some() {
+        let live = live::Live::shared();
+        if let Some(listener) = metrics_listener {
+            tokio::spawn(http::serve(listener, metrics::handler(live.clone())));
+        }
+        if let Some(listener) = api_listener {
+            tokio::spawn(http::serve(listener, api::handler(live.clone(), cli::kline_cache(&matches))));
+        }
+        if let Some(listener) = ws_listener {
+            tokio::spawn(fanout::serve(listener, live.clone()));
+        }
+        ui.tx.send(Msg::Live(live))?;
+    }
+    // after `Msg::Record` and `Msg::Live`, so that the recording and the live state get the infos
+    if replay.is_none() {
+        tokio::spawn(get_symbols_async(ui.tx.clone()));
+    }
+    if let Some(fee_pct) = matches.get_one::<f64>("fee") {
+        ui.tx.send(Msg::SetFee(*fee_pct))?;
+    }
+    if let Some(command) = matches.get_one::<String>("alert-command") {
+        let min_interval = matches.get_one::<u64>("alert-command-interval")
+                                  .map(|s| s*1000).unwrap_or(alerts::ALERT_COMMAND_INTERVAL_MILLIS);
+        ui.tx.send(Msg::AlertCommand(command.clone(), min_interval))?;
+    }
+
+    let listen_keys_handle = tokio::spawn(listen_keys(ui.tx.clone(), ui.input_mode.clone()));
 
-    let listen_keys_handle = tokio::spawn(listen_keys(ui.tx.clone()));
+    if let Some(file) = replay {
+        // the UI keeps running when the replay is finished
+        let control = Arc::new(replay::Control::new(speed));
+        ui.tx.send(Msg::Replay(control.clone()))?;
+        ui.tx.send(Msg::Msg(format!("Replaying {}... ", file)))?;
+        tokio::spawn(replay::replay(file, ui.tx, control));
+        future::select(ui.handle, listen_keys_handle).await;
+        return Ok(());
+    }
 
     ui.tx.send(Msg::Msg(String::from("Starting stream... ")))?;
-    let ws_task = tokio::spawn(ws(URI_WS_TICKER, ui.tx));
+    let ws_task = tokio::spawn(stream::ws(stream::URI_WS_TICKER, ui.tx, Msg::WS));
 
     future::select(ws_task, future::select(ui.handle, listen_keys_handle)).await;
     Ok(())
@@ -0,0 +1,61 @@
+//! Prometheus metrics of the live feed
+//!
+//! The endpoint `/metrics` renders the latest update of every symbol in the `Live` state in the
+//! Prometheus text format.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::http::{Request, Response, Handler};
+use crate::live::{Live, Latest, SharedLive};
+use std::fmt::Write;
+use std::sync::Arc;
+
+/// The metrics in the Prometheus text format, update ages relative to `now` (millis)
+pub fn render(live: &Live, now: u64) -> String {
+    let mut out = String::new();
+    // sorted for a stable output
+    let mut latest: Vec<(&Symbol, &Latest)> = live.latest.iter().collect();
+    latest.sort_by(|a, b| a.0[..].cmp(&b.0[..]));
+    let mut gauge = |name: &str, help: &str, value: &dyn Fn(&Latest) -> f64| {
+        let _ = writeln!(out, "# HELP {} {}\n# TYPE {} gauge", name, help, name);
+        for (symbol, latest) in latest.iter() {
+            let _ = writeln!(out, "{}{{symbol=\"{}\"}} {}", name, symbol, value(latest));
+        }
+    };
+    gauge("coinlive_price", "Latest price", &|l| dec_to_f64(l.px));
+    gauge("coinlive_price_change_24h_percent", "Price change over 24h in percent", &|l| l.change_24h());
+    gauge("coinlive_last_update_age_seconds", "Seconds since the latest update was received",
+          &|l| now.saturating_sub(l.ts_rec) as f64/1000.0);
+    gauge("coinlive_websocket_latency_seconds", "Seconds between exchange timestamp and receipt of the latest update",
+          &|l| (l.ts_rec as f64-l.ts as f64)/1000.0);
+    let _ = writeln!(out, "# HELP coinlive_websocket_messages_total Websocket messages received");
+    let _ = writeln!(out, "# TYPE coinlive_websocket_messages_total counter");
+    let _ = writeln!(out, "coinlive_websocket_messages_total {}", live.messages);
+    out
+}
+
+/// Handler of the endpoint `/metrics`
+pub fn handler(live: SharedLive) -> Handler {
+    Arc::new(move |request: &Request| {
+        if request.path != "/metrics" {
+            return Response::error(404, "Not found, try /metrics");
+        }
+        match live.lock() {
+            Ok(live) => Response::ok("text/plain; version=0.0.4", render(&live, now_timestamp())),
+            Err(_) => Response::error(500, "Metrics unavailable")
+        }
+    })
+}
+
+#[test]
+fn test_render() {
+    let mut live = Live::default();
+    live.update(1000, &[mock_update("ETHBTC", 900, "50", "100"), mock_update("BTCUSDT", 800, "110", "100")]);
+    live.update(2000, &[mock_update("ETHBTC", 1500, "150", "100")]);
+    let text = render(&live, 4000);
+    assert!(text.contains("# TYPE coinlive_price gauge\ncoinlive_price{symbol=\"BTCUSDT\"} 110\ncoinlive_price{symbol=\"ETHBTC\"} 150\n"), "{}", text);
+    assert!(text.contains("coinlive_price_change_24h_percent{symbol=\"ETHBTC\"} 50\n"), "{}", text);
+    assert!(text.contains("coinlive_last_update_age_seconds{symbol=\"BTCUSDT\"} 3\n"), "{}", text);
+    assert!(text.contains("coinlive_websocket_latency_seconds{symbol=\"ETHBTC\"} 0.5\n"), "{}", text);
+    assert!(text.ends_with("coinlive_websocket_messages_total 2\n"), "{}", text);
+}
@@ -0,0 +1,109 @@
+//! Portfolio of holdings with live valuation
+//!
+//! Holdings are written one per line as asset, quantity and cost basis per unit, e.g.
+//!
+//! ```text
+//! quote EUR
+//! BTC 0.5 30000
+//! ETH 4   1800
+//! DOT 100 6.5
+//! ```
+//!
+//! The optional `quote` line sets the currency the portfolio is valued in (default `USDT`),
+//! the cost basis is in this currency, too.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use inlinable_string::InlineString;
+
+/// A single holding
+#[derive(Debug, Clone, PartialEq)]
+pub struct Holding {
+    pub asset: Symbol,
+    pub qty: f64,
+    pub cost: f64,      // cost basis per unit in the portfolio's quote currency
+}
+
+/// Valuation of a single holding
+#[derive(Debug, Clone)]
+pub struct Valuation {
+    pub asset: Symbol,
+    pub qty: f64,
+    pub px: f64,        // price in the portfolio's quote currency, NAN if unknown
+    pub value: f64,     // qty*px
+    pub cost: f64,      // qty*cost basis
+    pub pnl: f64,       // value-cost
+    pub pnl_pct: f64,   // pnl/cost in percent
+    pub weight: f64,    // value/total value in percent
+}
+
+/// The portfolio
+#[derive(Debug, Clone)]
+pub struct Portfolio {
+    pub quote: Symbol,
+    pub holdings: Vec<Holding>,
+}
+
+impl Portfolio {
+    /// Parse a portfolio, see module documentation for the syntax. `#` starts a comment.
+    pub fn parse(text: &str) -> Result<Portfolio, String> {
+        let mut quote = InlineString::from("USDT");
+        let mut holdings = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() { continue; }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let err = |what: &str| format!("Line {}: {} in '{}'", i+1, what, line);
+            match words.as_slice() {
+                [kw, q] if kw.eq_ignore_ascii_case("quote") => { quote = parse_symbol(&q.to_uppercase()).map_err(|_| err("bad quote currency"))?; },
+                [asset, qty, cost] => {
+                    holdings.push(Holding {
+                        asset: parse_symbol(&asset.to_uppercase()).map_err(|_| err("bad asset"))?,
+                        qty: qty.parse().map_err(|_| err("bad quantity"))?,
+                        cost: cost.parse().map_err(|_| err("bad cost basis"))?,
+                    });
+                },
+                _ => return Err(err("expected 'ASSET QUANTITY COST'"))
+            }
+        }
+        Ok(Portfolio { quote: quote, holdings: holdings })
+    }
+    /// Value the holdings. `price` returns the price of an asset in the portfolio's quote currency.
+    ///
+    /// Returns the valuations and the total valuation (with asset `TOTAL`). Holdings without price
+    /// are excluded from the totals and weights.
+    pub fn value(self: &Self, price: &dyn Fn(&Symbol) -> Option<f64>) -> (Vec<Valuation>, Valuation) {
+        let mut out: Vec<Valuation> = self.holdings.iter().map(|h| {
+            let px = if h.asset == self.quote { Some(1.0) } else { price(&h.asset) }.unwrap_or(f64::NAN);
+            let value = h.qty*px;
+            let cost = h.qty*h.cost;
+            Valuation { asset: h.asset.clone(), qty: h.qty, px: px, value: value, cost: cost,
+                        pnl: value-cost, pnl_pct: (value-cost)/cost*100.0, weight: f64::NAN }
+        }).collect();
+        let valued = out.iter().filter(|v| !v.px.is_nan());
+        let value: f64 = valued.clone().map(|v| v.value).sum();
+        let cost: f64 = valued.map(|v| v.cost).sum();
+        for v in out.iter_mut() { v.weight = v.value/value*100.0; }
+        let total = Valuation { asset: InlineString::from("TOTAL"), qty: f64::NAN, px: f64::NAN, value: value, cost: cost,
+                                pnl: value-cost, pnl_pct: (value-cost)/cost*100.0, weight: 100.0 };
+        (out, total)
+    }
+}
+
+#[test]
+fn test_value() -> Result<(), Box<dyn std::error::Error>> {
+    let portfolio = Portfolio::parse("quote EUR\n# comment\nBTC 0.5 30000\neth 2 1000 # more comment\nEUR 1000 1\nXYZ 1 1\n")?;
+    assert_eq!(portfolio.quote, "EUR");
+    assert_eq!(portfolio.holdings.len(), 4);
+    let (vals, total) = portfolio.value(&|asset| match &**asset { "BTC" => Some(40000.0), "ETH" => Some(1500.0), _ => None });
+    assert_eq!(vals[0].value, 20000.0);
+    assert_eq!(vals[1].pnl, 1000.0);
+    assert_eq!(vals[1].pnl_pct, 50.0);
+    assert!(vals[3].value.is_nan());
+    assert_eq!(total.value, 24000.0);
+    assert_eq!(total.cost, 18000.0);
+    assert_eq!(vals[2].weight, 1000.0/24000.0*100.0);
+    assert!(Portfolio::parse("BTC lots 1").is_err());
+    assert!(Portfolio::parse(&format!("{} 1 1", "X".repeat(31))).is_err());
+    Ok(())
+}
@@ -0,0 +1,98 @@
+//! Cross rates between any two assets
+//!
+//! All live markets form a graph with the assets as nodes. Each market `BASEQUOTE` with price `px`
+//! gives an edge `BASE->QUOTE` with rate `px` and the inverse edge `QUOTE->BASE` with rate `1/px`.
+//! The rate between two assets is the product of the rates along the shortest path (fewest hops).
+//! Among paths of equal length the one through the more liquid markets is used.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::ui::MarketState;
+use std::collections::{HashMap, VecDeque};
+
+/// Longest route that is considered
+const MAX_HOPS: usize = 4;
+
+/// Conversion graph over the live markets
+pub struct Rates {
+    edges: HashMap<Symbol, Vec<(Symbol, f64)>>,  // asset -> (asset, rate), most liquid market first
+}
+
+impl Rates {
+    /// Build the graph from `(base, quote, price)` triples, most liquid market first
+    pub fn from_prices<'a, I>(prices: I) -> Self where I: Iterator<Item=(&'a Symbol, &'a Symbol, f64)> {
+        let mut edges: HashMap<Symbol, Vec<(Symbol, f64)>> = HashMap::new();
+        for (base, quote, px) in prices {
+            if !(px > 0.0 && px.is_finite()) { continue; }
+            edges.entry(base.clone()).or_default().push((quote.clone(), px));
+            edges.entry(quote.clone()).or_default().push((base.clone(), 1.0/px));
+        }
+        Rates { edges: edges }
+    }
+    /// Build the graph from the `infos` (sorted by volume) and the live `markets`
+    pub fn new(infos: &[Info], markets: &HashMap<Symbol, MarketState>) -> Self {
+        Rates::from_prices(infos.iter().filter_map(|info| {
+            markets.get(&info.symbol).map(|mkt| (&info.base, &info.quote, dec_to_f64(mkt.px())))
+        }))
+    }
+    /// Shortest route from `from` to `to` as list of assets including both ends
+    pub fn route(self: &Self, from: &Symbol, to: &Symbol) -> Option<Vec<Symbol>> {
+        if from == to { return Some(vec![from.clone()]); }
+        // breadth first search, remembering the predecessor of each visited asset
+        let mut prev: HashMap<&Symbol, &Symbol> = HashMap::new();
+        let mut queue: VecDeque<(&Symbol, usize)> = VecDeque::new();
+        queue.push_back((from, 0));
+        prev.insert(from, from);
+        while let Some((asset, hops)) = queue.pop_front() {
+            if hops >= MAX_HOPS { continue; }
+            for (next, _) in self.edges.get(asset)?.iter() {
+                if prev.contains_key(next) { continue; }
+                prev.insert(next, asset);
+                if next == to {
+                    let mut route = vec![to.clone()];
+                    let mut a = asset;
+                    while a != from { route.push(a.clone()); a = prev[a]; }
+                    route.push(from.clone());
+                    route.reverse();
+                    return Some(route);
+                }
+                queue.push_back((next, hops+1));
+            }
+        }
+        None
+    }
+    /// Rate of the direct edge `from->to`
+    fn edge(self: &Self, from: &Symbol, to: &Symbol) -> Option<f64> {
+        self.edges.get(from)?.iter().find(|(q, _)| q == to).map(|(_, px)| *px)
+    }
+    /// Price of one unit of `from` in `to`
+    pub fn rate(self: &Self, from: &Symbol, to: &Symbol) -> Option<f64> {
+        let route = self.route(from, to)?;
+        route.windows(2).map(|w| self.edge(&w[0], &w[1])).product()
+    }
+    /// Convert `amount` of `from` into `to`
+    pub fn convert(self: &Self, amount: f64, from: &Symbol, to: &Symbol) -> Option<f64> {
+        self.rate(from, to).map(|r| amount*r)
+    }
+}
+
+#[test]
+fn test_route() {
+    let s = mock_symbol;
+    let markets = [
+        (s("BTC"), s("USDT"), 40000.0),
+        (s("ETH"), s("BTC"), 0.05),
+        (s("BTC"), s("EUR"), 36000.0),
+        (s("DOT"), s("ETH"), 0.004),
+        (s("XYZ"), s("ABC"), 1.0),
+    ];
+    let rates = Rates::from_prices(markets.iter().map(|(b, q, px)| (b, q, *px)));
+    assert_eq!(rates.rate(&s("BTC"), &s("USDT")), Some(40000.0));
+    assert_eq!(rates.rate(&s("USDT"), &s("BTC")), Some(1.0/40000.0));
+    assert_eq!(rates.route(&s("ETH"), &s("EUR")), Some(vec![s("ETH"), s("BTC"), s("EUR")]));
+    assert_eq!(rates.rate(&s("ETH"), &s("EUR")), Some(0.05*36000.0));
+    assert_eq!(rates.route(&s("DOT"), &s("EUR")).map(|r| r.len()), Some(4));
+    assert_eq!(rates.convert(2.0, &s("EUR"), &s("EUR")), Some(2.0));
+    assert_eq!(rates.rate(&s("XYZ"), &s("BTC")), None);
+    assert_eq!(rates.rate(&s("FOO"), &s("BTC")), None);
+}
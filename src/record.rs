@@ -0,0 +1,117 @@
+//! Recording of the live websocket feed
+//!
+//! Every `Msg::WS` is written with its receive timestamp as one JSON object per line, e.g.
+//!
+//! ```text
+//! {"ts":1672531200123,"ws":"[{\"e\":\"24hrTicker\",\"E\":1672531200001,...}]"}
+//! ```
+//!
+//! When the symbols are downloaded a snapshot of them is written as `{"ts":...,"infos":[...]}`,
+//! so that a recording can be replayed without network. The file is gzip compressed.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use serde::{Serialize, Deserialize};
+use flate2::{Compression, write::GzEncoder, read::GzDecoder};
+use dec::Decimal64;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+/// `Info` in a form that can be serialized
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InfoRecord {
+    pub symbol: String,
+    pub base: String,
+    pub quote: String,
+    pub volume: String,
+}
+
+impl InfoRecord {
+    pub fn new(info: &Info) -> Self {
+        InfoRecord { symbol: info.symbol.to_string(), base: info.base.to_string(), quote: info.quote.to_string(),
+                     volume: info.volume.to_string() }
+    }
+    /// The `Info`, an error if a symbol doesn't fit in a `Symbol`
+    pub fn info(self: &Self) -> Result<Info, String> {
+        Ok(Info { symbol: parse_symbol(&self.symbol)?, base: parse_symbol(&self.base)?,
+                  quote: parse_symbol(&self.quote)?, volume: self.volume.parse().unwrap_or(Decimal64::NAN) })
+    }
+}
+
+/// A single recorded message, either websocket payload or symbols snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub ts: u64,                    // receive timestamp (millis)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws: Option<String>,         // raw websocket payload
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub infos: Option<Vec<InfoRecord>>, // symbols snapshot
+}
+
+/// Writes `Record`s to a gzip compressed file
+#[derive(Debug)]
+pub struct Recorder {
+    path: String,
+    writer: GzEncoder<BufWriter<File>>,
+    pub count: usize,   // number of websocket messages written
+}
+
+impl Recorder {
+    /// Create the file `path`, an existing file is overwritten
+    pub fn create(path: &str) -> Result<Recorder, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create record file {}: {}", path, e))?;
+        Ok(Recorder { path: String::from(path), writer: GzEncoder::new(BufWriter::new(file), Compression::default()), count: 0 })
+    }
+    /// Write a single record
+    fn write_record(self: &mut Self, record: &Record) -> Result<(), String> {
+        let line = serde_json::to_string(record).map_err(|e| e.to_string())?;
+        writeln!(self.writer, "{}", line).map_err(|e| format!("Failed to write record file {}: {}", self.path, e))
+    }
+    /// Write the websocket payload `ws` received at `ts`
+    pub fn write(self: &mut Self, ts: u64, ws: &str) -> Result<(), String> {
+        self.write_record(&Record { ts: ts, ws: Some(String::from(ws)), infos: None })?;
+        self.count += 1;
+        Ok(())
+    }
+    /// Write a snapshot of the symbols
+    pub fn write_infos(self: &mut Self, ts: u64, infos: &[Info]) -> Result<(), String> {
+        self.write_record(&Record { ts: ts, ws: None, infos: Some(infos.iter().map(InfoRecord::new).collect()) })
+    }
+    /// Finish the compressed stream and flush the file
+    pub fn finish(self: Self) -> Result<(), String> {
+        let path = self.path;
+        self.writer.finish()
+            .and_then(|mut w| w.flush())
+            .map_err(|e| format!("Failed to finish record file {}: {}", path, e))
+    }
+}
+
+/// Read the records of the file `path`
+pub fn read_records(path: &str) -> Result<impl Iterator<Item=Result<Record, String>>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open record file {}: {}", path, e))?;
+    let path = String::from(path);
+    Ok(BufReader::new(GzDecoder::new(file)).lines().enumerate().map(move |(i, line)| {
+        let line = line.map_err(|e| format!("Failed to read record file {}: {}", path, e))?;
+        serde_json::from_str(&line).map_err(|e| format!("Bad record in {} line {}: {}", path, i+1, e))
+    }))
+}
+
+#[test]
+fn test_record() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("coinlive-record-{}.ndjson.gz", std::process::id()));
+    let path = path.to_str().ok_or("bad temp path")?;
+    let mut recorder = Recorder::create(path)?;
+    recorder.write_infos(0, &[mock_info("BTC", "USDT", "123.5")])?;
+    recorder.write(1, "[{\"s\":\"BTCUSDT\"}]")?;
+    recorder.write(2, "1234\n")?;
+    assert_eq!(recorder.count, 2);
+    recorder.finish()?;
+    let records: Vec<Record> = read_records(path)?.collect::<Result<_, _>>()?;
+    std::fs::remove_file(path)?;
+    assert_eq!(records.len(), 3);
+    let infos = records[0].infos.as_ref().ok_or("no infos")?;
+    assert_eq!(infos[0].info()?.symbol, "BTCUSDT");
+    assert_eq!(infos[0].info()?.volume, "123.5".parse()?);
+    assert_eq!(records[2], Record { ts: 2, ws: Some(String::from("1234\n")), infos: None });
+    Ok(())
+}
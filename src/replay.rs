@@ -0,0 +1,129 @@
+//! Replay of a recorded websocket feed
+//!
+//! The records written with `--record` are sent to the `UI` as `Msg::Infos` and `Msg::WS`, just
+//! like the live feed, with the original timing divided by `speed`. The replay can be paused and,
+//! while paused, stepped one message at a time. Websocket payloads the `UI` can't parse are skipped
+//! and counted.
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::ui::Msg;
+use crate::record::read_records;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Polling interval while the replay is paused
+const PAUSE_SLEEP_MILLIS: u64 = 50;
+
+/// Pause and step control, shared between the `UI` and the replay task
+#[derive(Debug)]
+pub struct Control {
+    pub speed: f64,         // replay speed, 1 is the original timing
+    paused: AtomicBool,
+    steps: AtomicUsize,     // messages to send while paused
+}
+
+impl Control {
+    pub fn new(speed: f64) -> Self {
+        Control { speed: speed, paused: AtomicBool::new(false), steps: AtomicUsize::new(0) }
+    }
+    /// Pause or resume, returns true if paused
+    pub fn toggle_pause(self: &Self) -> bool {
+        !self.paused.fetch_xor(true, Ordering::Relaxed)
+    }
+    pub fn is_paused(self: &Self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+    /// Send the next message while paused
+    pub fn step(self: &Self) {
+        self.steps.fetch_add(1, Ordering::Relaxed);
+    }
+    /// Wait until the replay is resumed or a step is requested
+    async fn wait(self: &Self) {
+        while self.is_paused() {
+            if self.steps.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| n.checked_sub(1)).is_ok() { return; }
+            tokio::time::sleep(Duration::from_millis(PAUSE_SLEEP_MILLIS)).await;
+        }
+    }
+}
+
+/// Replay the file `path` into `tx`
+pub async fn replay(path: String, tx: UnboundedSender<Msg>, control: Arc<Control>) -> Result<(), String> {
+    let ui_failed = |e| format!("UI failed: {:?}", e);
+    let result = replay_records(&path, &tx, &control).await;
+    match &result {
+        Ok((n, 0)) => tx.send(Msg::Msg(format!("Replay of {} finished, {} messages", path, n))).map_err(ui_failed)?,
+        Ok((n, bad)) => tx.send(Msg::Msg(format!("Replay of {} finished, {} messages, {} bad records skipped", path, n, bad)))
+                          .map_err(ui_failed)?,
+        Err(e) => tx.send(Msg::Msg(e.clone())).map_err(ui_failed)?,
+    }
+    result.map(|_| ())
+}
+
+/// A recorded websocket payload the `UI` can handle: price updates or a latency timestamp
+fn is_valid_ws(ws: &String, buf: &mut Vec<Update>) -> bool {
+    buf.clear();
+    parse_updates(ws, buf).is_ok() || ws.parse::<u64>().is_ok()
+}
+
+/// Send the records of `path`, returns the number of websocket messages sent and of bad ones skipped
+async fn replay_records(path: &str, tx: &UnboundedSender<Msg>, control: &Control) -> Result<(usize, usize), String> {
+    let mut last_ts: Option<u64> = None;
+    let (mut n, mut bad): (usize, usize) = (0, 0);
+    let mut buf: Vec<Update> = Vec::new();
+    for (i, record) in read_records(path)?.enumerate() {
+        let record = record?;
+        if let Some(infos) = record.infos {
+            let infos = infos.iter().map(|i| i.info()).collect::<Result<Vec<Info>, String>>()
+                             .map_err(|e| format!("Bad record in {} line {}: {}", path, i+1, e))?;
+            tx.send(Msg::Infos(sort_infos(infos))).map_err(|e| format!("UI failed: {:?}", e))?;
+        }
+        if let Some(ws) = record.ws {
+            if !is_valid_ws(&ws, &mut buf) {
+                bad += 1;
+                continue;
+            }
+            if let Some(last_ts) = last_ts {
+                let delay = (record.ts.saturating_sub(last_ts) as f64/control.speed) as u64;
+                if delay > 0 && !control.is_paused() {
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                }
+            }
+            control.wait().await;
+            last_ts = Some(record.ts);
+            tx.send(Msg::WS(record.ts, ws)).map_err(|e| format!("UI failed: {:?}", e))?;
+            n += 1;
+        }
+    }
+    Ok((n, bad))
+}
+
+#[tokio::test]
+async fn test_replay_in_order() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::temp_dir().join(format!("coinlive-replay-{}.ndjson.gz", std::process::id()));
+    let path = path.to_str().ok_or("bad temp path")?.to_string();
+    let mut recorder = crate::record::Recorder::create(&path)?;
+    recorder.write_infos(0, &[mock_info("BTC", "USDT", "1")])?;
+    recorder.write(1000, "900")?;
+    recorder.write(1500, "{bad")?;
+    recorder.write(2000, "1900")?;
+    recorder.finish()?;
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let control = Arc::new(Control::new(1000.0));
+    assert!(control.toggle_pause());
+    control.step();
+    let task = tokio::spawn(replay(path.clone(), tx, control.clone()));
+    assert!(matches!(rx.recv().await, Some(Msg::Infos(infos)) if infos.len() == 1));
+    assert!(matches!(rx.recv().await, Some(Msg::WS(1000, ws)) if ws == "900"));
+    // paused after the single step
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(rx.try_recv().is_err());
+    assert!(!control.toggle_pause());
+    assert!(matches!(rx.recv().await, Some(Msg::WS(2000, ws)) if ws == "1900"));
+    assert!(matches!(rx.recv().await, Some(Msg::Msg(msg)) if msg.ends_with("2 messages, 1 bad records skipped")));
+    task.await??;
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
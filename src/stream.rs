@@ -0,0 +1,119 @@
+//! Websocket streams that forward their messages to the `UI`
+#![allow(dead_code)]
+
+use crate::utils::{now_timestamp, Symbol};
+use crate::ui::Msg;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use futures_util::{StreamExt, stream::SplitStream};
+use url::Url;
+use std::time::Duration;
+
+/// Binance 24h ticker stream endpoint
+pub const URI_WS_TICKER: &str = "wss://stream.binance.com:9443/ws/!ticker@arr";
+
+/// Delay before the first reconnect, doubled after every failed attempt
+const RECONNECT_MIN_MILLIS: u64 = 1000;
+
+/// Maximum delay between reconnects
+const RECONNECT_MAX_MILLIS: u64 = 60_000;
+
+/// Read half of a websocket stream
+pub type WsRead = SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>;
+
+/// Connect to the websocket at `uri`.
+///
+/// Connection errors are reported to the `UI`, in which case `None` is returned.
+pub async fn connect(uri: &str, ui_tx: &UnboundedSender<Msg>) -> Result<Option<WsRead>, String> {
+    let uri: Url = Url::parse(uri).map_err(|e| format!("Bad url: {:?}", e))?;
+    match connect_async(uri).await {
+        Ok((ws_stream, _)) => {
+            let (_, read) = ws_stream.split();
+            Ok(Some(read))
+        },
+        Err(e) => {
+            ui_tx.send(Msg::Msg(format!("Error connecting: {:?}", e)))
+                 .map_err(|e| format!("UI failed: {:?}", e))?;
+            Ok(None)
+        }
+    }
+}
+
+/// Forward every message of `read` to the `UI`.
+///
+/// `wrap` turns the receive timestamp (millis) and the message into a `Msg`, e.g. `Msg::WS`.
+pub async fn forward(mut read: WsRead, ui_tx: UnboundedSender<Msg>, wrap: fn(u64, String) -> Msg) -> Result<(), String> {
+    loop {
+        let next = read.next().await;
+        let now = now_timestamp();
+        match next {
+            Some(msg) => {
+                match msg {
+                    Ok(msg)  => {
+                        let msg = msg.to_string();
+                        ui_tx.send(wrap(now, msg))
+                             .map_err(|e| format!("UI failed: {:?}", e))?;
+                    },
+                    Err(e) => {
+                        ui_tx.send(Msg::Msg(format!("Error: {:?}", e)))
+                             .map_err(|e| format!("UI failed: {:?}", e))?;
+                        return Err(format!("Websocket error: {:?}", e));
+                    }
+                }
+            },
+            None => {
+                ui_tx.send(Msg::Msg(String::from("Stream end")))
+                     .map_err(|e| format!("UI failed: {:?}", e))?;
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Websocket stream
+pub async fn ws(uri: &str, ui_tx: UnboundedSender<Msg>, wrap: fn(u64, String) -> Msg) -> Result<(), String> {
+    let read = match connect(uri, &ui_tx).await? {
+        Some(read) => read,
+        None => return Ok(())
+    };
+    ui_tx.send(Msg::Msg(format!("Websocket connected: {}", uri)))
+         .map_err(|e| format!("UI failed: {:?}", e))?;
+    ui_tx.send(Msg::Msg(String::from("Starting..."))).expect("UI failed");
+    forward(read, ui_tx, wrap).await
+}
+
+/// Websocket stream that reconnects when the connection fails or ends.
+///
+/// Returns only when the `UI` is gone.
+pub async fn ws_reconnect(uri: &str, ui_tx: UnboundedSender<Msg>, wrap: fn(u64, String) -> Msg) -> Result<(), String> {
+    let mut delay = RECONNECT_MIN_MILLIS;
+    loop {
+        let start = now_timestamp();
+        if let Err(e) = ws(uri, ui_tx.clone(), wrap).await {
+            if ui_tx.is_closed() { return Err(e); }
+        }
+        if ui_tx.is_closed() { return Ok(()); }
+        // a connection that lasted a while was fine, start again with a short delay
+        if now_timestamp()-start > RECONNECT_MAX_MILLIS { delay = RECONNECT_MIN_MILLIS; }
+        ui_tx.send(Msg::Msg(format!("Reconnecting in {}s...", delay/1000)))
+             .map_err(|e| format!("UI failed: {:?}", e))?;
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+        delay = (delay*2).min(RECONNECT_MAX_MILLIS);
+    }
+}
+
+/// Binance combined stream endpoint, `{}` is the `/` separated list of streams
+const URI_WS_COMBINED: &str = "wss://stream.binance.com:9443/stream?streams={}";
+
+/// Book ticker streams of `symbols` combined into one websocket, sent as `Msg::BookTicker`
+pub async fn book_ticker_stream(symbols: Vec<Symbol>, ui_tx: UnboundedSender<Msg>) -> Result<(), String> {
+    let streams: Vec<String> = symbols.iter().map(|s| format!("{}@bookTicker", s.to_lowercase())).collect();
+    let uri = URI_WS_COMBINED.replace("{}", &streams.join("/"));
+    let read = match connect(&uri, &ui_tx).await? {
+        Some(read) => read,
+        None => return Ok(())
+    };
+    forward(read, ui_tx, Msg::BookTicker).await
+}
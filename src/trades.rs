@@ -0,0 +1,79 @@
+//! Time and sales of a symbol from the aggTrade websocket stream
+#![allow(dead_code)]
+
+use crate::utils::*;
+use crate::ui::Msg;
+use crate::stream;
+use std::collections::VecDeque;
+use tokio::sync::mpsc::UnboundedSender;
+use dec::Decimal64;
+
+/// Number of trades kept in the `Tape`
+const TAPE_LEN: usize = 500;
+
+/// Binance aggregate trade stream endpoint, `{}` is the lowercase symbol
+const URI_WS_AGG_TRADE: &str = "wss://stream.binance.com:9443/ws/{}@aggTrade";
+
+/// The most recent trades of a symbol and the running buy/sell volumes
+pub struct Tape {
+    pub symbol: Symbol,
+    trades: VecDeque<Trade>,    // most recent trade first
+    pub buy_volume: Decimal64,  // aggressive buy volume since the tape was started
+    pub sell_volume: Decimal64, // aggressive sell volume since the tape was started
+}
+
+impl Tape {
+    /// Create new empty `Tape`
+    pub fn new(symbol: Symbol) -> Self {
+        Tape {
+            symbol: symbol, trades: VecDeque::with_capacity(TAPE_LEN),
+            buy_volume: Decimal64::from(0), sell_volume: Decimal64::from(0)
+        }
+    }
+    /// Add a trade, trades of other symbols are ignored
+    pub fn push(self: &mut Self, trade: Trade) {
+        if trade.symbol != self.symbol { return; }
+        if trade.is_buy { self.buy_volume += trade.qty; }
+        else            { self.sell_volume += trade.qty; }
+        if self.trades.len() >= TAPE_LEN { self.trades.pop_back(); }
+        self.trades.push_front(trade);
+    }
+    /// The `n` most recent trades, most recent first
+    pub fn trades(self: &Self, n: usize) -> impl Iterator<Item=&Trade> {
+        self.trades.iter().take(n)
+    }
+    /// Buy/sell volume imbalance (buy-sell)/(buy+sell) between -1 and 1, NAN without trades
+    pub fn imbalance(self: &Self) -> f64 {
+        let buy = dec_to_f64(self.buy_volume);
+        let sell = dec_to_f64(self.sell_volume);
+        if buy+sell > 0.0 { (buy-sell)/(buy+sell) } else { f64::NAN }
+    }
+}
+
+/// Aggregate trade stream for `symbol`, the trades are sent as `Msg::Trade`
+pub async fn trade_stream(symbol: Symbol, ui_tx: UnboundedSender<Msg>) -> Result<(), String> {
+    let uri = URI_WS_AGG_TRADE.replace("{}", &symbol.to_lowercase());
+    let read = match stream::connect(&uri, &ui_tx).await? {
+        Some(read) => read,
+        None => return Ok(())
+    };
+    stream::forward(read, ui_tx, Msg::Trade).await
+}
+
+#[test]
+fn test_imbalance() -> Result<(), Box<dyn std::error::Error>> {
+    let mut tape = Tape::new(mock_symbol("BTCUSDT"));
+    assert!(tape.imbalance().is_nan());
+    let msgs = [
+        r#"{"e":"aggTrade","E":2,"s":"BTCUSDT","a":1,"p":"100.0","q":"3.0","f":1,"l":1,"T":1,"m":false,"M":true}"#,
+        r#"{"e":"aggTrade","E":3,"s":"BTCUSDT","a":2,"p":"99.0","q":"1.0","f":2,"l":2,"T":2,"m":true,"M":true}"#,
+        r#"{"e":"aggTrade","E":4,"s":"ETHUSDT","a":3,"p":"9.0","q":"9.0","f":3,"l":3,"T":3,"m":true,"M":true}"#,
+    ];
+    for msg in msgs.iter() {
+        tape.push(parse_trade(msg)?);
+    }
+    assert_eq!(tape.trades(10).count(), 2);
+    assert_eq!(tape.trades(10).next().map(|t| t.is_buy), Some(false));
+    assert_eq!(tape.imbalance(), 0.5);
+    Ok(())
+}
@@ -16,8 +16,48 @@ pub mod graph;
 pub mod search;
 /// Pretty printing of floats and Decimal
 pub mod nice;
+/// The order book page
+pub mod order_book;
+/// The depth chart page
+pub mod depth_chart;
+/// The trades page
+pub mod trades;
+/// The alerts page
+pub mod alerts;
+/// The input popup
+pub mod input;
+/// The portfolio page
+pub mod portfolio;
+/// The converter popup
+pub mod converter;
+/// The triangular arbitrage page
+pub mod arbitrage;
+/// The volatility and correlation page
+pub mod analytics;
+/// The market heatmap page
+pub mod heatmap;
+/// Output of widgets with ANSI escape codes
+pub mod ansi;
 
 use crate::utils::*;
+use crate::book::{self, OrderBook};
+use crate::trades::Tape;
+use crate::stream;
+use crate::alerts::{Alerts, Rule, Triggered, Hook};
+use input::{Input, InputPurpose};
+use converter::Converter;
+use crate::portfolio::Portfolio;
+use crate::rates::Rates;
+use crate::arbitrage::{scan_markets, DEFAULT_FEE_PCT};
+use crate::analytics::Series;
+use crate::record::Recorder;
+use crate::replay;
+use crate::cache::{KlineCache, get_klines_cached};
+use crate::live::SharedLive;
+use crate::export;
+use std::io::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::cell::RefCell;
 use std::rc::Rc;
 use tui::{
@@ -29,36 +69,155 @@ use tui::{
     terminal::Frame,
 };
 use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
-use std::collections::HashMap;
-use chrono::Local;
+use std::collections::{HashMap, VecDeque};
+use chrono::{Local, TimeZone};
 use std::marker::Copy;
 use dec::Decimal64;
 use inlinable_string::{InlineString};
 
+/// Number of samples kept in the rolling price history of `MarketState`
+const HISTORY_LEN: usize = 240;
+
+/// Sampling interval of the rolling price history in millis
+const HISTORY_INTERVAL_MILLIS: u64 = 60_000;
+
+/// Width of the sparkline column in characters
+pub const SPARKLINE_WIDTH: usize = 8;
+
+/// Number of symbols per book ticker websocket, all listed symbols are streamed
+const BOOK_TICKER_SYMBOLS: usize = 200;
+
+/// How long a triggered alert is shown in the message bar
+const ALERT_DISPLAY_MILLIS: u64 = 10_000;
+
+/// Number of assets (by volume) on the volatility and correlation page
+const ANALYTICS_ASSETS: usize = 12;
+
+/// Kline intervals selectable on the volatility and correlation page
+const ANALYTICS_INTERVALS: [Interval; 5] = [Interval::I15m, Interval::I1h, Interval::I4h, Interval::I1d, Interval::I1w];
+
+/// Lookback windows (number of bars) selectable on the volatility and correlation page
+const ANALYTICS_WINDOWS: [usize; 6] = [30, 60, 90, 180, 365, 1000];
+
+/// Characters used to draw sparklines, from lowest to highest
+const SPARKLINE_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
 /// Stores the relevant market data with some extra rendering information
 pub struct MarketState {
     px: Decimal64,
     ts: u64,
     last_px: Decimal64,
     px_24h: Decimal64,
+    history: VecDeque<f64>,     // rolling price history, one sample per `HISTORY_INTERVAL_MILLIS`
+    history_bucket: u64,        // ts / `HISTORY_INTERVAL_MILLIS` of the last sample
+    bid: Decimal64,             // best bid from the book ticker
+    ask: Decimal64,             // best ask from the book ticker
 }
 
 impl MarketState {
     /// Create new `MarketState` with NANs.
-    fn new() -> Self {
-        MarketState { px: Decimal64::NAN, ts: 0, last_px: Decimal64::NAN, px_24h:Decimal64::NAN }
+    pub fn new() -> Self {
+        MarketState { 
+            px: Decimal64::NAN, ts: 0, last_px: Decimal64::NAN, px_24h:Decimal64::NAN,
+            history: VecDeque::with_capacity(HISTORY_LEN), history_bucket: 0,
+            bid: Decimal64::NAN, ask: Decimal64::NAN
+        }
     }
     /// Update `MarketState` with data from `Update`
-    fn update(self: &mut Self, update: &Update) {
+    pub fn update(self: &mut Self, update: &Update) {
         self.last_px = self.px;
         self.px = update.px;
         self.px_24h = update.px_24h;
         self.ts = update.ts;
+        self.update_history();
+    }
+    /// Update best bid and ask with data from `BookTicker`
+    fn update_book(self: &mut Self, ticker: &BookTicker) {
+        self.bid = ticker.bid;
+        self.ask = ticker.ask;
+    }
+    /// Forget best bid and ask when the book ticker is stopped
+    fn clear_book(self: &mut Self) {
+        self.bid = Decimal64::NAN;
+        self.ask = Decimal64::NAN;
+    }
+    /// Feed the current price into the rolling history. 
+    /// 
+    /// The last sample is overwritten until the next sampling interval starts.
+    fn update_history(self: &mut Self) {
+        let px = dec_to_f64(self.px);
+        if !px.is_finite() { return; }
+        let bucket = self.ts / HISTORY_INTERVAL_MILLIS;
+        match self.history.back_mut() {
+            Some(last) if bucket == self.history_bucket => { *last = px; },
+            _ => {
+                if self.history.len() >= HISTORY_LEN { self.history.pop_front(); }
+                self.history.push_back(px);
+                self.history_bucket = bucket;
+            }
+        }
+    }
+    /// Make a sparkline string of the price history that has `width` chars
+    pub fn sparkline_string(self: &Self, width: usize) -> String {
+        let n = self.history.len();
+        if n < 2 || width == 0 { return format!("{:>width$}", "-", width=width); }
+        let w = width.min(n);
+        // downsample by taking the last sample of each of the `w` chunks
+        let samples: Vec<f64> = (0..w).map(|i| self.history[((i+1)*n)/w-1]).collect();
+        let min = samples.iter().cloned().fold(f64::MAX, f64::min);
+        let max = samples.iter().cloned().fold(f64::MIN, f64::max);
+        let top = (SPARKLINE_CHARS.len()-1) as f64;
+        let mut s: String = samples.iter().map(|x| {
+            let i = if max > min { ((x-min)/(max-min)*top).round() as usize } else { 0 };
+            SPARKLINE_CHARS[i.min(SPARKLINE_CHARS.len()-1)]
+        }).collect();
+        while s.chars().count() < width { s.insert(0, ' '); }
+        s
+    }
+    /// Generate a style for the sparkline, i.e. the trend over the whole history
+    pub fn style_sparkline(self: &Self) -> Style {
+        match (self.history.front(), self.history.back()) {
+            (Some(first), Some(last)) if last > first => Style::default().fg(Color::Green),
+            (Some(first), Some(last)) if last < first => Style::default().fg(Color::Red),
+            _ => Style::default().fg(Color::Gray)
+        }
+    }
+    /// Last price
+    pub fn px(self: &Self) -> Decimal64 {
+        self.px
+    }
+    /// Best bid, NAN if there is no book ticker
+    pub fn bid(self: &Self) -> Decimal64 {
+        self.bid
+    }
+    /// Best ask, NAN if there is no book ticker
+    pub fn ask(self: &Self) -> Decimal64 {
+        self.ask
+    }
+    /// Change of the price in the last 24h in percent
+    pub fn change_24h(self: &Self) -> f64 {
+        dec_to_f64((self.px-self.px_24h)/self.px_24h)*100.0
+    }
+    /// Change of the price in the last `minutes` in percent, NAN if the history is too short
+    pub fn change_since(self: &Self, minutes: u32) -> f64 {
+        let n = minutes as usize * 60_000 / HISTORY_INTERVAL_MILLIS as usize;
+        if n == 0 || n >= self.history.len() { return f64::NAN; }
+        let then = self.history[self.history.len()-1-n];
+        (dec_to_f64(self.px)-then)/then*100.0
     }
     /// Make a nicely formatted price string
     pub fn price_string(self: &Self) -> String {
         fmt_dec(self.px)
     }
+    /// Make nicely formatted best bid and best ask strings
+    pub fn bid_ask_strings(self: &Self) -> (String, String) {
+        (fmt_dec(self.bid), fmt_dec(self.ask))
+    }
+    /// Make a string of the bid/ask spread in basis points of the mid price that has 6 width
+    pub fn spread_string(self: &Self) -> String {
+        let bps = dec_to_f64((self.ask-self.bid)/(self.ask+self.bid)*Decimal64::from(20000));
+        if bps.is_finite() { format!("{:>6.1}", bps) } else { format!("{:>6}", "-") }
+    }
     /// Make a percentage string that has 6 width !TODO! improve
     pub fn percentage_string(self: &Self) -> String {
         let hundred: Decimal64 = "100".parse().expect("INTERNAL ERROR");
@@ -98,8 +257,24 @@ impl MarketState {
 
 /// Messages that the `UI` can receive
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]    // `Msg::Msg` is the plain text message
 pub enum Msg {
     WS(u64, String),    // timestamp (millis) and websocket data
+    Depth(u64, String), // timestamp (millis) and diff depth websocket data
+    DepthSnapshot(Symbol, DepthSnapshot), // Downloaded order book snapshot
+    AnalyticsBars(Interval, Vec<(Symbol, Vec<Bar>)>, usize), // Downloaded klines for the analytics page, number of failed downloads
+    Trade(u64, String), // timestamp (millis) and aggregate trade websocket data
+    BookTicker(u64, String), // timestamp (millis) and book ticker websocket data
+    AddAlert(String),   // Add an alert rule
+    AlertCommand(String, u64), // Run command (at most every n millis) when an alert is triggered
+    SetPortfolio(Portfolio), // Portfolio loaded from file
+    SetFee(f64),        // Fee per trade in percent for the arbitrage page
+    Record(Recorder),   // Record the websocket feed
+    Replay(Arc<replay::Control>), // The feed is replayed from a recording
+    KlineCache(KlineCache), // Cache klines on disk
+    Live(SharedLive),   // Keep the live state served over HTTP up to date
+    Pause,              // On ' ' pause or resume the replay
+    Step,               // On '.' replay the next message while paused
     Infos(Vec<Info>),   // Downloaded infos for each symbol
     Msg(String),        // info message to UI
     PriceList,          // On 'l' key press show PriceList
@@ -107,7 +282,24 @@ pub enum Msg {
     Graph(Option<u32>), // On 'g' display graph with given time scale, or stored time scale if Nothing
     TogglePercent,      // On '%' key press
     ToggleExtended,     // On 'x' key press
+    ToggleSparkline,    // On 'S' key press
+    ToggleBook,         // On 'b' key press
+    ToggleBell,         // On 'B' key press
     Search,             // On 's' show the search widget
+    OrderBook,          // On 'o' show the order book of the selected symbol
+    DepthChart,         // On 'd' show the depth chart of the selected symbol
+    Trades,             // On 'r' show the trades of the selected symbol
+    Alerts,             // On 'A' show the alerts page
+    Portfolio,          // On 'p' show the portfolio page
+    Converter,          // On 'c' open the converter popup
+    Arbitrage,          // On 'T' show the triangular arbitrage page
+    Analytics,          // On 'm' show the volatility and correlation page
+    Heatmap,            // On 'H' show the market heatmap
+    Export,             // On 'e' export the klines (graph page) or the prices (other pages)
+    NewAlert,           // On 'n' open the input popup for a new alert rule
+    Input(char),        // Character typed into the input popup
+    Backspace,          // On backspace in the input popup
+    Delete,             // On delete
     ArrowUp,            // On arrow up
     ArrowDown,          // On arrow down
     ArrowLeft,          // On srrow left
@@ -130,6 +322,14 @@ enum UIView {
     PriceTable, // display PriceTable
     Graph,      // display graph
     Search,     // display search widget
+    OrderBook,  // display order book
+    DepthChart, // display depth chart
+    Trades,     // display trades
+    Alerts,     // display alerts
+    Portfolio,  // display portfolio
+    Arbitrage,  // display triangular arbitrage loops
+    Analytics,  // display volatility and correlation matrix
+    Heatmap,    // display market heatmap
     Empty,      // display PriceTable
     Help,       // display help
     About,      // display help
@@ -147,6 +347,8 @@ pub struct UIState {
     ui_mode_back: Option<UIView>,       // where to go back to if ESC is pressed
     show_percent: bool,                 // 
     extended: bool,                     // extended view of table page
+    show_sparkline: bool,               // show sparkline column in list and table pages
+    show_book: bool,                    // show bid, ask and spread columns in list and table pages
     ts_last_update: u64,                // ts of last market update
     lookup: Option<HashMap<Symbol, Info>>,
     infos: Option<Vec<Info>>,
@@ -155,6 +357,28 @@ pub struct UIState {
     time_scale: u32,                    // time scale for graph
     cursor_ix: u16,                     // x position of symbol in search widget
     cursor_iy: u16,                     // y position of symbol in search widget
+    book: Option<OrderBook>,            // order book of the selected symbol
+    depth_task: Option<tokio::task::JoinHandle<Result<(), String>>>,   // diff depth stream feeding `book`
+    tape: Option<Tape>,                 // trades of the selected symbol
+    trade_task: Option<tokio::task::JoinHandle<Result<(), String>>>,   // aggregate trade stream feeding `tape`
+    book_ticker_tasks: Vec<tokio::task::JoinHandle<Result<(), String>>>,  // book ticker streams feeding `markets`
+    alerts: Alerts,                     // alert rules and triggered alerts
+    alert_ix: usize,                    // selected rule on alerts page
+    last_alert: Option<Triggered>,      // most recent triggered alert, shown in message bar
+    bell: bool,                         // ring the terminal bell when an alert is triggered
+    hook: Option<Hook>,                 // command run when an alert is triggered
+    input: Option<Input>,               // the input popup if it is open
+    portfolio: Option<Portfolio>,       // holdings for the portfolio page
+    converter: Option<Converter>,       // the converter popup if it is open
+    fee_pct: f64,                       // fee per trade in percent for the arbitrage page
+    analytics: Option<Series>,          // close prices for the volatility and correlation page
+    analytics_task: Option<tokio::task::JoinHandle<Result<(), String>>>,   // klines download for `analytics`
+    analytics_interval: usize,          // index into `ANALYTICS_INTERVALS`
+    analytics_window: usize,            // index into `ANALYTICS_WINDOWS`
+    recorder: Option<Recorder>,         // records the websocket feed
+    replay: Option<Arc<replay::Control>>,  // pause and step control if the feed is replayed
+    kline_cache: Option<KlineCache>,    // on-disk kline cache
+    live: Option<SharedLive>,           // live state served over HTTP
 }
 
 impl UIState {
@@ -168,6 +392,8 @@ impl UIState {
             ui_mode_back: None,
             show_percent: false,
             extended: true,
+            show_sparkline: false,
+            show_book: false,
             ts_last_update: 0,
             lookup: None,
             infos: None,
@@ -176,24 +402,62 @@ impl UIState {
             time_scale: 0,
             cursor_ix: 0,
             cursor_iy: 0,
+            book: None,
+            depth_task: None,
+            tape: None,
+            trade_task: None,
+            book_ticker_tasks: Vec::new(),
+            alerts: Alerts::new(),
+            alert_ix: 0,
+            last_alert: None,
+            bell: false,
+            hook: None,
+            input: None,
+            portfolio: None,
+            converter: None,
+            fee_pct: DEFAULT_FEE_PCT,
+            analytics: None,
+            analytics_task: None,
+            analytics_interval: 3,
+            analytics_window: 2,
+            recorder: None,
+            replay: None,
+            kline_cache: None,
+            live: None,
         }
     }
-    fn update(self: &mut Self, updates: &Vec<Update>) {
+    /// Update markets and check the alert rules, returns the triggered alerts
+    fn update(self: &mut Self, updates: &Vec<Update>) -> Vec<Triggered> {
+        let mut triggered = Vec::new();
         if let Some(lookup) = &self.lookup {
             for u in updates {
                 if u.ts > self.ts_last_update { self.ts_last_update = u.ts; }
                 let info = lookup.get(&u.symbol);
-                if let Some(_) = info {
-                    self.markets.entry(u.symbol.clone()).or_insert(MarketState::new()).update(&u);
+                if info.is_some() {
+                    let mkt = self.markets.entry(u.symbol.clone()).or_insert(MarketState::new());
+                    mkt.update(&u);
+                    if !self.alerts.rules.is_empty() {
+                        let mut t = self.alerts.check(u.ts, &u.symbol, mkt.px(), mkt.change_24h(), &|m| mkt.change_since(m));
+                        triggered.append(&mut t);
+                    }
                 }
             }
         }
+        triggered
+    }
+    fn update_book(self: &mut Self, ticker: &BookTicker) {
+        if let Some(lookup) = &self.lookup {
+            if lookup.contains_key(&ticker.symbol) {
+                self.markets.entry(ticker.symbol.clone()).or_insert(MarketState::new()).update_book(ticker);
+            }
+        }
     }
 }
 /// Encapsulates the `UI`
 pub struct UI {
     pub tx: UnboundedSender<Msg>,
     pub handle: tokio::task::JoinHandle<()>,
+    pub input_mode: Arc<AtomicBool>,    // true while the input popup is open, keys are then sent as `Msg::Input`
 }
 
 impl UI {
@@ -201,6 +465,9 @@ impl UI {
     pub fn new(mut terminal: Term) -> Self {
         terminal.clear().expect("Terminal failed!");
         let (tx, mut rx) = unbounded_channel();
+        let ui_tx = tx.clone();
+        let input_mode = Arc::new(AtomicBool::new(false));
+        let ui_input_mode = input_mode.clone();
         let handle = tokio::spawn( async move {
             let mut state = UIState::new();
             let mut buf: Vec<Update> = Vec::with_capacity(2000);    // buffer for parse_updates
@@ -208,18 +475,122 @@ impl UI {
             while let Some(msg) = rx.recv().await {
                 match msg {
                     Msg::Infos(infos_) => {
-                        state.infos = Some(infos_.iter().cloned().filter(|i| i.quote != "TUSD" && i.quote != "BUSD" && i.quote != "USDC").collect());
+                        if let Some(recorder) = &mut state.recorder {
+                            if let Err(e) = recorder.write_infos(now_timestamp(), &infos_) {
+                                state.message = e;
+                                state.recorder = None;
+                            }
+                        }
+                        if let Some(Ok(mut live)) = state.live.as_ref().map(|l| l.lock()) {
+                            live.set_infos(&infos_);
+                        }
+                        state.infos = Some(infos_.iter().filter(|i| i.quote != "TUSD" && i.quote != "BUSD" && i.quote != "USDC").cloned().collect());
                         state.lookup = Some(infos_to_lookup(&infos_));
                         state.ui_mode = UIView::PriceList;
+                        UI::book_ticker(&mut state, &ui_tx);
                     },
                     Msg::WS(ts_rec, msg) => {
+                        if let Some(recorder) = &mut state.recorder {
+                            if let Err(e) = recorder.write(ts_rec, &msg) {
+                                state.message = e;
+                                state.recorder = None;
+                            }
+                        }
                         if let Ok(us) = parse_updates(&msg, &mut buf) {
-                            state.update(&us);
+                            if let Some(Ok(mut live)) = state.live.as_ref().map(|l| l.lock()) {
+                                live.update(ts_rec, us);
+                            }
+                            let triggered = state.update(&us);
+                            UI::alert(&mut state, triggered, &ui_tx);
                         } else if let Ok(ts) = msg.parse::<u64>() {
                             state.latency = ts_rec-ts;
                         } else {
+                            // an unparsable message concerns only itself
                             state.message = format!("{:?}", msg);
-                            break;
+                        }
+                    },
+                    Msg::Depth(_, msg) => {
+                        if let Some(book) = &mut state.book {
+                            if let Ok(update) = parse_depth_update(&msg) {
+                                if let Err(e) = book.apply(&update) {
+                                    state.message = format!("Order book out of sync ({:?}), resyncing", e);
+                                    UI::order_book(&mut state, &ui_tx);
+                                }
+                            }
+                        }
+                    },
+                    Msg::DepthSnapshot(symbol, snapshot) => {
+                        if let Some(book) = &mut state.book {
+                            if book.symbol == symbol {
+                                if let Err(e) = book.apply_snapshot(snapshot) {
+                                    state.message = format!("Order book out of sync ({:?}), resyncing", e);
+                                    UI::order_book(&mut state, &ui_tx);
+                                }
+                            }
+                        }
+                    },
+                    Msg::AnalyticsBars(interval, bars, failed) => {
+                        // a download for another interval was superseded
+                        if interval == ANALYTICS_INTERVALS[state.analytics_interval] {
+                            state.analytics = Some(Series::new(interval, bars));
+                            state.message = format!("Volatility and correlation of {} {} bars{}",
+                                                    ANALYTICS_WINDOWS[state.analytics_window], interval,
+                                                    if failed > 0 { format!(", {} assets failed to download", failed) } else { String::new() });
+                        }
+                    },
+                    Msg::Trade(_, msg) => {
+                        if let Some(tape) = &mut state.tape {
+                            if let Ok(trade) = parse_trade(&msg) {
+                                tape.push(trade);
+                            }
+                        }
+                    },
+                    Msg::BookTicker(_, msg) => {
+                        if let Ok(ticker) = parse_book_ticker(&msg) {
+                            state.update_book(&ticker);
+                        }
+                    },
+                    Msg::AddAlert(text) => {
+                        match Rule::parse(&text) {
+                            Ok(rule) => {
+                                state.message = format!("Added alert '{}'", rule.text);
+                                state.alerts.add(rule);
+                            },
+                            Err(e) => { state.message = e; }
+                        }
+                    },
+                    Msg::AlertCommand(command, min_interval) => {
+                        state.hook = Some(Hook::new(command, min_interval));
+                    },
+                    Msg::SetPortfolio(portfolio) => {
+                        state.portfolio = Some(portfolio);
+                    },
+                    Msg::SetFee(fee_pct) => {
+                        state.fee_pct = fee_pct;
+                    },
+                    Msg::Record(recorder) => {
+                        state.recorder = Some(recorder);
+                    },
+                    Msg::Replay(control) => {
+                        state.replay = Some(control);
+                    },
+                    Msg::KlineCache(cache) => {
+                        state.kline_cache = Some(cache);
+                    },
+                    Msg::Live(live) => {
+                        state.live = Some(live);
+                    },
+                    Msg::Pause => {
+                        state.message = match &state.replay {
+                            Some(control) if control.toggle_pause() => String::from("Replay paused, press '.' to step"),
+                            Some(_) => String::from("Replay resumed"),
+                            None => String::from("Pause is only available when replaying")
+                        };
+                    },
+                    Msg::Step => {
+                        match &state.replay {
+                            Some(control) if control.is_paused() => control.step(),
+                            _ => { state.message = String::from("Step is only available when the replay is paused"); }
                         }
                     },
                     Msg::Msg(msg) => {
@@ -237,12 +608,111 @@ impl UI {
                         state.time_scale = scale.unwrap_or(state.time_scale);
                         UI::graph(&mut state, &mut terminal).await;
                     },
+                    Msg::OrderBook => {
+                        state.ui_mode = UIView::OrderBook;
+                        state.message = format!("Show order book for {}", state.symbol);
+                        if state.book.as_ref().map(|b| b.symbol != state.symbol).unwrap_or(true) {
+                            UI::order_book(&mut state, &ui_tx);
+                        }
+                    },
+                    Msg::DepthChart => {
+                        state.ui_mode = UIView::DepthChart;
+                        state.message = format!("Show depth chart for {}", state.symbol);
+                        if state.book.as_ref().map(|b| b.symbol != state.symbol).unwrap_or(true) {
+                            UI::order_book(&mut state, &ui_tx);
+                        }
+                    },
+                    Msg::Trades => {
+                        state.ui_mode = UIView::Trades;
+                        state.message = format!("Show trades for {}", state.symbol);
+                        if state.tape.as_ref().map(|t| t.symbol != state.symbol).unwrap_or(true) {
+                            UI::trades(&mut state, &ui_tx);
+                        }
+                    },
+                    Msg::Portfolio => {
+                        state.ui_mode = UIView::Portfolio;
+                        state.message = match &state.portfolio {
+                            Some(portfolio) => format!("Portfolio in {}", portfolio.quote),
+                            None => String::from("No portfolio, start with --portfolio FILE")
+                        };
+                    },
+                    Msg::Arbitrage => {
+                        state.ui_mode = UIView::Arbitrage;
+                        state.message = format!("Triangular arbitrage, {}% fee per trade", state.fee_pct);
+                    },
+                    Msg::Heatmap => {
+                        state.ui_mode = UIView::Heatmap;
+                        state.message = String::from("Show market heatmap, sized by volume, colored by 24h change");
+                    },
+                    Msg::Analytics => {
+                        state.ui_mode = UIView::Analytics;
+                        UI::analytics(&mut state, &ui_tx);
+                    },
+                    Msg::Alerts => {
+                        state.ui_mode = UIView::Alerts;
+                        state.message = format!("{} alert rules", state.alerts.rules.len());
+                    },
+                    Msg::NewAlert => {
+                        state.input = Some(Input::new("New alert, e.g. 'BTC above 70000', 'ETH moves 3% in 5m', 'any 24h +20%'", InputPurpose::Alert));
+                        ui_input_mode.store(true, Ordering::Relaxed);
+                    },
+                    Msg::Export => {
+                        let input = if state.ui_mode == UIView::Graph && state.klines.is_some() {
+                            let mut input = Input::new("Export klines to file (.csv, .json or .parquet)", InputPurpose::ExportKlines);
+                            input.text = format!("{}-{}.csv", state.symbol, UI::time_scale_interval(state.time_scale));
+                            input
+                        } else {
+                            let mut input = Input::new("Export prices to file (.csv, .json or .parquet)", InputPurpose::ExportSnapshot);
+                            input.text = format!("prices-{}.csv", Local::now().format("%Y%m%d-%H%M%S"));
+                            input
+                        };
+                        state.input = Some(input);
+                        ui_input_mode.store(true, Ordering::Relaxed);
+                    },
+                    Msg::Converter => {
+                        state.converter = Some(Converter::new());
+                        ui_input_mode.store(true, Ordering::Relaxed);
+                    },
+                    Msg::Input(c) => {
+                        if let Some(converter) = &mut state.converter { converter.input(c); }
+                        if let Some(input) = &mut state.input { if c != '\t' { input.text.push(c); } }
+                    },
+                    Msg::Backspace => {
+                        if let Some(converter) = &mut state.converter { converter.backspace(); }
+                        if let Some(input) = &mut state.input { input.text.pop(); }
+                    },
+                    Msg::ArrowUp | Msg::ArrowDown if state.converter.is_some() => {
+                        let step = if let Msg::ArrowUp = msg { -1 } else { 1 };
+                        let assets = UI::assets(&state);
+                        if let Some(converter) = &mut state.converter { converter.pick(&assets, step); }
+                    },
+                    Msg::Enter if state.converter.is_some() => {
+                        if let Some(converter) = &mut state.converter { converter.input('\n'); }
+                    },
+                    Msg::Delete => {
+                        if state.ui_mode == UIView::Alerts {
+                            if let Some(rule) = state.alerts.remove(state.alert_ix) {
+                                state.message = format!("Removed alert '{}'", rule.text);
+                                if state.alert_ix > 0 && state.alert_ix >= state.alerts.rules.len() { state.alert_ix -= 1; }
+                            }
+                        }
+                    },
                     Msg::Search => {
                         state.ui_mode_back = Some(state.ui_mode);
                         state.ui_mode = UIView::Search;
                         state.message = String::from("Select symbol");
                     },
+                    Msg::ArrowUp | Msg::ArrowDown if state.ui_mode == UIView::Analytics => {
+                        let n = ANALYTICS_WINDOWS.len();
+                        state.analytics_window = if let Msg::ArrowUp = msg { (state.analytics_window+1).min(n-1) }
+                                                 else { state.analytics_window.saturating_sub(1) };
+                        state.message = format!("Volatility and correlation of {} {} bars",
+                                                ANALYTICS_WINDOWS[state.analytics_window], ANALYTICS_INTERVALS[state.analytics_interval]);
+                    },
                     Msg::ArrowUp => {
+                        if state.ui_mode == UIView::Alerts && state.alert_ix > 0 {
+                            state.alert_ix -= 1;
+                        }
                         if state.ui_mode == UIView::Search {
                             if state.cursor_iy > 0 { 
                                 state.cursor_iy -= 1;
@@ -251,11 +721,21 @@ impl UI {
                         }
                     },
                     Msg::ArrowDown => {
+                        if state.ui_mode == UIView::Alerts && state.alert_ix+1 < state.alerts.rules.len() {
+                            state.alert_ix += 1;
+                        }
                         if state.ui_mode == UIView::Search {
                             state.cursor_iy += 1;   // ! height needs to be checked elsewhere!
                             cursor_moved = true;
                         }
                     },
+                    Msg::ArrowLeft | Msg::ArrowRight if state.ui_mode == UIView::Analytics => {
+                        let n = ANALYTICS_INTERVALS.len();
+                        state.analytics_interval = if let Msg::ArrowLeft = msg { (state.analytics_interval+n-1)%n }
+                                                   else { (state.analytics_interval+1)%n };
+                        state.analytics = None;
+                        UI::analytics(&mut state, &ui_tx);
+                    },
                     Msg::ArrowLeft => {
                         if state.ui_mode == UIView::Search {
                             if state.cursor_ix > 0 { 
@@ -278,7 +758,33 @@ impl UI {
                         }
                     },
                     Msg::Enter => {
-                        if state.ui_mode == UIView::Search {
+                        if let Some(input) = state.input.take() {
+                            ui_input_mode.store(false, Ordering::Relaxed);
+                            match input.purpose {
+                                InputPurpose::Alert => {
+                                    ui_tx.send(Msg::AddAlert(input.text)).expect("UI failed");
+                                },
+                                InputPurpose::ExportKlines => {
+                                    if let Some(klines) = &state.klines {
+                                        state.message = match export::Format::from_path(&input.text)
+                                                .and_then(|format| export::write_klines(&input.text, format, klines)) {
+                                            Ok(()) => format!("Exported {} klines to {}", klines.len(), input.text),
+                                            Err(e) => e
+                                        };
+                                    }
+                                },
+                                InputPurpose::ExportSnapshot => {
+                                    if let Some(infos) = &state.infos {
+                                        let rows = export::snapshot_rows(infos, &state.markets);
+                                        state.message = match export::Format::from_path(&input.text)
+                                                .and_then(|format| export::write_snapshot(&input.text, format, &rows)) {
+                                            Ok(()) => format!("Exported {} prices to {}", rows.len(), input.text),
+                                            Err(e) => e
+                                        };
+                                    }
+                                },
+                            }
+                        } else if state.ui_mode == UIView::Search {
                             state.message = format!("Graph {}", state.symbol);
                             state.ui_mode_back = Some(state.ui_mode);
                             state.ui_mode = UIView::Graph;
@@ -295,6 +801,22 @@ impl UI {
                         if state.extended { state.message = String::from("Show extended"); }
                         else { state.message = String::from("Show reduced"); }
                     },
+                    Msg::ToggleSparkline => {
+                        state.show_sparkline = !state.show_sparkline;
+                        if state.show_sparkline { state.message = String::from("Show sparklines"); }
+                        else { state.message = String::from("Hide sparklines"); }
+                    },
+                    Msg::ToggleBook => {
+                        state.show_book = !state.show_book;
+                        if state.show_book { state.message = String::from("Show bid/ask"); }
+                        else { state.message = String::from("Hide bid/ask"); }
+                        UI::book_ticker(&mut state, &ui_tx);
+                    },
+                    Msg::ToggleBell => {
+                        state.bell = !state.bell;
+                        if state.bell { state.message = String::from("Alert bell on"); }
+                        else { state.message = String::from("Alert bell off"); }
+                    },
                     Msg::Help => {
                         state.ui_mode_back = Some(state.ui_mode);
                         state.ui_mode = UIView::Help;
@@ -305,6 +827,11 @@ impl UI {
                         state.ui_mode = UIView::About;
                         state.message = String::from("About");
                     },
+                    Msg::Esc if state.input.is_some() || state.converter.is_some() => {
+                        state.input = None;
+                        state.converter = None;
+                        ui_input_mode.store(false, Ordering::Relaxed);
+                    },
                     Msg::Esc => {
                         state.ui_mode = state.ui_mode_back.unwrap_or(UIView::PriceList);
                         state.ui_mode_back = None;
@@ -312,10 +839,18 @@ impl UI {
                     },
                     Msg::Stop => { 
                         state.message = String::from("Stop");
+                        if let Some(recorder) = state.recorder.take() {
+                            let count = recorder.count;
+                            state.message = match recorder.finish() {
+                                Ok(()) => format!("Stop, recorded {} messages", count),
+                                Err(e) => e
+                            };
+                        }
                         UI::draw(&mut state, &mut terminal);
                         return; 
                     }
                 }
+                UI::stop_streams(&mut state);
                 UI::draw(&mut state, &mut terminal); 
                 if cursor_moved {
                     state.message = format!("SEL {}", state.symbol);
@@ -323,11 +858,88 @@ impl UI {
                 }
             }
         });
-        UI { tx: tx, handle: handle }
+        UI { tx: tx, handle: handle, input_mode: input_mode }
     }
-    /// Draw Graph
-    pub async fn graph(mut state: &mut UIState, mut terminal: &mut Term) {
-        let interval: Interval = match state.time_scale {
+    /// All assets, quote assets first, each sorted by volume
+    fn assets(state: &UIState) -> Vec<Symbol> {
+        match &state.infos {
+            Some(infos) => {
+                let (bases, quotes) = sort_base_quote(infos);
+                let mut assets = quotes.clone();
+                assets.extend(bases.into_iter().filter(|b| !quotes.contains(b)));
+                assets
+            },
+            None => Vec::new()
+        }
+    }
+    /// Show triggered alerts in the message bar, ring the bell and run the alert command
+    fn alert(state: &mut UIState, triggered: Vec<Triggered>, ui_tx: &UnboundedSender<Msg>) {
+        if let Some(hook) = &mut state.hook {
+            let now = now_timestamp();
+            for t in triggered.iter() {
+                if !hook.run(t, now, ui_tx) {
+                    state.message = format!("Alert command skipped for {} (rate limit)", t.symbol);
+                }
+            }
+        }
+        if let Some(t) = triggered.into_iter().last() {
+            state.last_alert = Some(t);
+            if state.bell {
+                print!("\x07");
+                std::io::stdout().flush().unwrap_or(());
+            }
+        }
+    }
+    /// (Re)start the order book of the selected symbol
+    fn order_book(state: &mut UIState, ui_tx: &UnboundedSender<Msg>) {
+        if let Some(task) = state.depth_task.take() { task.abort(); }
+        state.book = Some(OrderBook::new(state.symbol.clone()));
+        state.depth_task = Some(tokio::spawn(book::depth_stream(state.symbol.clone(), ui_tx.clone())));
+    }
+    /// (Re)start the book ticker streams of all listed symbols while bid/ask are shown, otherwise stop them
+    fn book_ticker(state: &mut UIState, ui_tx: &UnboundedSender<Msg>) {
+        for task in state.book_ticker_tasks.drain(..) { task.abort(); }
+        match &state.infos {
+            Some(infos) if state.show_book => {
+                // a websocket URL can't hold all streams
+                state.book_ticker_tasks = infos.chunks(BOOK_TICKER_SYMBOLS).map(|chunk| {
+                    let symbols = chunk.iter().map(|i| i.symbol.clone()).collect();
+                    tokio::spawn(stream::book_ticker_stream(symbols, ui_tx.clone()))
+                }).collect();
+            },
+            _ => {
+                for mkt in state.markets.values_mut() { mkt.clear_book(); }
+            }
+        }
+    }
+    /// (Re)start the trades tape of the selected symbol
+    fn trades(state: &mut UIState, ui_tx: &UnboundedSender<Msg>) {
+        if let Some(task) = state.trade_task.take() { task.abort(); }
+        state.tape = Some(Tape::new(state.symbol.clone()));
+        state.trade_task = Some(tokio::spawn(crate::trades::trade_stream(state.symbol.clone(), ui_tx.clone())));
+    }
+    /// Stop the order book and trades streams and the analytics download when their pages are left,
+    /// Help and About keep them
+    fn stop_streams(state: &mut UIState) {
+        let shown = |view: UIView| state.ui_mode == view || state.ui_mode_back == Some(view);
+        let book_shown = shown(UIView::OrderBook) || shown(UIView::DepthChart);
+        let trades_shown = shown(UIView::Trades);
+        let analytics_shown = shown(UIView::Analytics);
+        if !book_shown {
+            if let Some(task) = state.depth_task.take() { task.abort(); }
+            state.book = None;
+        }
+        if !trades_shown {
+            if let Some(task) = state.trade_task.take() { task.abort(); }
+            state.tape = None;
+        }
+        if !analytics_shown {
+            if let Some(task) = state.analytics_task.take() { task.abort(); }
+        }
+    }
+    /// Kline interval of graph time scale `time_scale`
+    fn time_scale_interval(time_scale: u32) -> Interval {
+        match time_scale {
             1 => Interval::I5m,
             2 => Interval::I15m,
             3 => Interval::I30m,
@@ -338,13 +950,18 @@ impl UI {
             8 => Interval::I12h,
             9 => Interval::I1d,
             _ => Interval::I1m,
-        };
+        }
+    }
+    /// Draw Graph
+    pub async fn graph(mut state: &mut UIState, mut terminal: &mut Term) {
+        let interval = UI::time_scale_interval(state.time_scale);
         state.message = format!("Getting {} klines for {}", interval.str(), state.symbol);
         UI::draw(&mut state, &mut terminal);
-        match get_klines(&state.symbol, &interval).await {
-            Ok(klines) => {
+        match get_klines_cached(state.kline_cache.as_ref(), &state.symbol, &interval).await {
+            Ok((klines, offline)) => {
                 state.ui_mode = UIView::Graph;
-                state.message = format!("Show {} klines for {}", interval.str(), state.symbol);
+                state.message = format!("Show {} klines for {}{}", interval.str(), state.symbol,
+                                        if offline { " (cached, offline)" } else { "" });
                 state.klines = Some(klines);
             },
             Err(e) => {
@@ -352,6 +969,24 @@ impl UI {
             }
         }
     }
+    /// (Re)start the download of the klines of the top `ANALYTICS_ASSETS` assets for the volatility
+    /// and correlation page
+    fn analytics(state: &mut UIState, ui_tx: &UnboundedSender<Msg>) {
+        let symbols: Vec<(Symbol, Symbol)> = match (&state.infos, &state.lookup) {
+            (Some(infos), Some(lookup)) => {
+                let (bases, _) = sort_base_quote(infos);
+                bases.into_iter()
+                     .filter_map(|base| parse_symbol(&format!("{}USDT", base)).ok().map(|symbol| (base, symbol)))
+                     .filter(|(_, symbol)| lookup.contains_key(symbol))
+                     .take(ANALYTICS_ASSETS)
+                     .collect()
+            },
+            _ => return
+        };
+        if let Some(task) = state.analytics_task.take() { task.abort(); }
+        let interval = ANALYTICS_INTERVALS[state.analytics_interval];
+        state.analytics_task = Some(tokio::spawn(crate::analytics::download(state.kline_cache.clone(), interval, symbols, ui_tx.clone())));
+    }
     /// Draw `UI`
     fn draw(state: &mut UIState, terminal: &mut Term) {
         terminal.draw(|f| {
@@ -369,13 +1004,15 @@ impl UI {
             match state.ui_mode {
                 UIView::PriceList => {
                     if let Some(infos) = &state.infos {
-                        let price_list = price_list::PriceList::new(&infos, &state.markets, state.show_percent);
+                        let price_list = price_list::PriceList::new(&infos, &state.markets, state.show_percent, 
+                                                                    state.show_sparkline, state.show_book);
                         f.render_widget(price_list, chunks[0]);
                     }
                 },
                 UIView::PriceTable => {
                     if let Some(infos) = &state.infos {
-                        let price_table = price_table::PriceTable::new(&infos, &state.markets, state.show_percent, state.extended);
+                        let price_table = price_table::PriceTable::new(&infos, &state.markets, state.show_percent, 
+                                                                       state.extended, state.show_sparkline, state.show_book);
                         f.render_widget(price_table, chunks[0]);
                     }
                 },
@@ -387,6 +1024,48 @@ impl UI {
                         }
                     }
                 },
+                UIView::OrderBook => {
+                    if let Some(book) = &state.book {
+                        f.render_widget(order_book::OrderBookLadder::new(book), chunks[0]);
+                    }
+                },
+                UIView::DepthChart => {
+                    if let Some(book) = &state.book {
+                        f.render_widget(depth_chart::DepthChart::new(book), chunks[0]);
+                    }
+                },
+                UIView::Trades => {
+                    if let Some(tape) = &state.tape {
+                        f.render_widget(trades::TradesTape::new(tape), chunks[0]);
+                    }
+                },
+                UIView::Alerts => {
+                    f.render_widget(alerts::AlertList::new(&state.alerts, state.alert_ix), chunks[0]);
+                },
+                UIView::Portfolio => {
+                    if let (Some(p), Some(infos)) = (&state.portfolio, &state.infos) {
+                        let rates = Rates::new(infos, &state.markets);
+                        let (valuations, total) = p.value(&|asset| rates.rate(asset, &p.quote));
+                        f.render_widget(portfolio::PortfolioTable::new(&p.quote, &valuations, &total), chunks[0]);
+                    }
+                },
+                UIView::Arbitrage => {
+                    if let Some(infos) = &state.infos {
+                        let loops = scan_markets(infos, &state.markets, state.fee_pct);
+                        f.render_widget(arbitrage::ArbitrageTable::new(&loops, state.fee_pct), chunks[0]);
+                    }
+                },
+                UIView::Heatmap => {
+                    if let Some(infos) = &state.infos {
+                        f.render_widget(heatmap::Heatmap::new(infos, &state.markets), chunks[0]);
+                    }
+                },
+                UIView::Analytics => {
+                    if let Some(series) = &state.analytics {
+                        let matrix = series.matrix(ANALYTICS_WINDOWS[state.analytics_window]+1);
+                        f.render_widget(analytics::CorrelationMatrix::new(&matrix), chunks[0]);
+                    }
+                },
                 UIView::Search => {
                     // The `Search` object needs to be able to modify i_symbol and cursor (ix, iy), so
                     // we use interior mutability via Rc<RefCell<...>>.
@@ -421,6 +1100,12 @@ impl UI {
                     about::draw_about(f, chunks[0]);
                 }
             }
+            if let Some(input) = &state.input {
+                input::draw_input(f, input, chunks[0]);
+            }
+            if let (Some(c), Some(infos)) = (&state.converter, &state.infos) {
+                converter::draw_converter(f, c, &Rates::new(infos, &state.markets), chunks[0]);
+            }
             UI::draw_message_bar(f, state, chunks[1]);
         }).expect("Failed to draw!");
     }
@@ -435,18 +1120,32 @@ impl UI {
             .constraints(
                 [ Constraint::Length(13)    // 13 chars in "| HH:MM:SS | "
                 , Constraint::Min(0)
-                , Constraint::Length(9)     // enough for 99999ms
+                , Constraint::Length(12)    // enough for 99999ms and REPLAY x100
                 ].as_ref()
             )
             .split(area);
-        let now = Local::now();
+        // when replaying the clock is the time of the last replayed update
+        let now = match &state.replay {
+            Some(_) if state.ts_last_update != 0 => Local.timestamp_millis_opt(state.ts_last_update as i64).single().unwrap_or_else(Local::now),
+            _ => Local::now()
+        };
+        let now_ms = now.timestamp_millis() as u64;
         let now_span = Spans::from(vec![
             Span::from("| "),
             Span::styled(format!("{}", now.format("%H:%M:%S")), Style::default().add_modifier(Modifier::ITALIC)),
             Span::from(" | ")
         ]);
-        let msg_span = Span::from(state.message.as_str());
-        let lat_span = if state.ts_last_update != 0 {
+        let msg_span = match &state.last_alert {
+            Some(t) if now_ms - t.ts.min(now_ms) < ALERT_DISPLAY_MILLIS => {
+                Span::styled(format!("ALERT {} {}: {}", t.symbol, fmt_dec(t.px), t.rule),
+                             Style::default().fg(Color::Black).bg(Color::Yellow).add_modifier(Modifier::BOLD))
+            },
+            _ => Span::from(state.message.as_str())
+        };
+        let lat_span = if let Some(control) = &state.replay {
+            let s = if control.is_paused() { String::from("PAUSED") } else { format!("REPLAY x{}", control.speed) };
+            Span::styled(s, Style::default().fg(Color::Cyan))
+        } else if state.ts_last_update != 0 {
             let delta = now.timestamp_millis() as u64-state.ts_last_update;
             let s = format!("{}ms", delta);
             let style = Style::default().fg(
@@ -462,4 +1161,19 @@ impl UI {
         f.render_widget(Paragraph::new(msg_span), chunks[1]);
         f.render_widget(Paragraph::new(lat_span).alignment(Alignment::Right), chunks[2]);
     }
-}
\ No newline at end of file
+}
+#[test]
+fn test_sparkline() {
+    let mut mkt = MarketState::new();
+    assert_eq!(mkt.sparkline_string(4), "   -");
+    for (i, px) in ["1", "2", "3", "4"].iter().enumerate() {
+        // two updates per minute, only the last one is kept
+        for ts in [0, 30_000].iter() {
+            mkt.update(&mock_update("BTCUSDT", i as u64*60_000+ts, px, px));
+        }
+    }
+    assert_eq!(mkt.history.len(), 4);
+    assert_eq!(mkt.sparkline_string(4), "▁▃▆█");
+    assert_eq!(mkt.sparkline_string(6), "  ▁▃▆█");
+    assert_eq!(mkt.sparkline_string(2), "▁█");
+}
@@ -0,0 +1,61 @@
+///! Widget `AlertList`
+use crate::utils::*;
+use crate::alerts::Alerts;
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Widget},
+    layout::{Rect},
+    text::{Span, Spans},
+    buffer::{Buffer}
+};
+use chrono::{Local, TimeZone};
+
+/// Widget AlertList
+///
+/// Shows the alert rules, with the selected rule highlighted, and the log of triggered alerts.
+pub struct AlertList<'a> {
+    alerts: &'a Alerts,
+    selected: usize,    // index of the selected rule
+}
+
+impl<'a> AlertList<'a> {
+    pub fn new(alerts: &'a Alerts, selected: usize) -> AlertList<'a> {
+        AlertList { alerts: alerts, selected: selected }
+    }
+}
+
+impl<'a> Widget for AlertList<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let bold = Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC);
+        let mut y: u16 = 0;
+        buf.set_spans(0, y, &Spans::from(vec![Span::styled("Rules", bold)]), area.width);
+        y += 1;
+        if self.alerts.rules.is_empty() {
+            buf.set_spans(0, y, &Spans::from(vec![Span::styled("No rules, press 'n' to add one", Style::default().fg(Color::Gray))]), area.width);
+            y += 1;
+        }
+        for (i, rule) in self.alerts.rules.iter().enumerate() {
+            if y >= area.height { return; }
+            let style = if i == self.selected { Style::default().fg(Color::Red).add_modifier(Modifier::BOLD) } else { Style::default() };
+            buf.set_spans(0, y, &Spans::from(vec![Span::styled(format!("{:>3} {}", i+1, rule.text), style)]), area.width);
+            y += 1;
+        }
+        y += 1;
+        if y >= area.height { return; }
+        buf.set_spans(0, y, &Spans::from(vec![Span::styled("Triggered", bold)]), area.width);
+        y += 1;
+        for t in self.alerts.log.iter() {
+            if y >= area.height { return; }
+            let ts = Local.timestamp_millis_opt(t.ts as i64).single()
+                          .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or(String::from("-"));
+            let spans = Spans::from(vec![
+                Span::styled(format!("{} ", ts), Style::default().fg(Color::Gray)),
+                Span::styled(format!("{:<12} ", t.symbol), Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(format!("{:>14} ", fmt_dec(t.px)), Style::default().fg(Color::Yellow)),
+                Span::raw(t.rule.clone()),
+            ]);
+            buf.set_spans(0, y, &spans, area.width);
+            y += 1;
+        }
+    }
+}
@@ -0,0 +1,56 @@
+///! Widget `CorrelationMatrix`
+use crate::analytics::Matrix;
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Widget},
+    layout::{Rect},
+    text::{Span, Spans},
+    buffer::{Buffer}
+};
+
+/// Width of a matrix cell in characters
+const CELL_WIDTH: usize = 6;
+
+/// Widget CorrelationMatrix
+///
+/// Shows the annualized volatility of each asset and the correlation matrix as heatmap.
+pub struct CorrelationMatrix<'a> {
+    matrix: &'a Matrix,
+}
+
+impl<'a> CorrelationMatrix<'a> {
+    pub fn new(matrix: &'a Matrix) -> CorrelationMatrix<'a> {
+        CorrelationMatrix { matrix: matrix }
+    }
+    /// Heatmap style for correlation `c`, green for positive, red for negative correlation
+    fn style_corr(c: f64) -> Style {
+        if !c.is_finite() { return Style::default().fg(Color::Gray); }
+        let level = (c.abs().min(1.0)*180.0) as u8;
+        let bg = if c >= 0.0 { Color::Rgb(0, level, 0) } else { Color::Rgb(level, 0, 0) };
+        Style::default().fg(Color::White).bg(bg)
+    }
+}
+
+impl<'a> Widget for CorrelationMatrix<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let bold = Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC);
+        let mut header = vec![Span::styled(format!("{:<8} {:>8} ", "Asset", "Vol %"), bold)];
+        for asset in self.matrix.assets.iter() {
+            header.push(Span::styled(format!("{:>width$}", &**asset, width=CELL_WIDTH), bold));
+        }
+        buf.set_spans(0, 0, &Spans::from(header), area.width);
+        for (i, asset) in self.matrix.assets.iter().enumerate() {
+            if i+2 > area.height as usize { break; }
+            let vol = self.matrix.vols[i];
+            let mut row = vec![
+                Span::styled(format!("{:<8} ", &**asset), Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(if vol.is_finite() { format!("{:>8.1} ", vol) } else { format!("{:>8} ", "-") }),
+            ];
+            for c in self.matrix.corr[i].iter() {
+                let s = if c.is_finite() { format!("{:>width$.2}", c, width=CELL_WIDTH) } else { format!("{:>width$}", "-", width=CELL_WIDTH) };
+                row.push(Span::styled(s, CorrelationMatrix::style_corr(*c)));
+            }
+            buf.set_spans(0, 1+i as u16, &Spans::from(row), area.width);
+        }
+    }
+}
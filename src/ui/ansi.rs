@@ -0,0 +1,85 @@
+///! Rendering of a `Buffer` as text with ANSI escape codes, for output without the terminal UI
+use tui::{
+    style::{Color, Modifier},
+    buffer::{Buffer, Cell},
+};
+
+/// SGR parameters of a foreground or `background` color
+pub fn color_code(color: Color, background: bool) -> String {
+    let (base, extended) = if background { (40, 48) } else { (30, 38) };
+    let code = match color {
+        Color::Reset => base+9,
+        Color::Black => base,
+        Color::Red => base+1,
+        Color::Green => base+2,
+        Color::Yellow => base+3,
+        Color::Blue => base+4,
+        Color::Magenta => base+5,
+        Color::Cyan => base+6,
+        Color::Gray => base+7,
+        Color::DarkGray => base+60,
+        Color::LightRed => base+61,
+        Color::LightGreen => base+62,
+        Color::LightYellow => base+63,
+        Color::LightBlue => base+64,
+        Color::LightMagenta => base+65,
+        Color::LightCyan => base+66,
+        Color::White => base+67,
+        Color::Rgb(r, g, b) => return format!("{};2;{};{};{}", extended, r, g, b),
+        Color::Indexed(i) => return format!("{};5;{}", extended, i),
+    };
+    code.to_string()
+}
+
+/// Escape sequence that sets the style of `cell`
+fn sgr(cell: &Cell) -> String {
+    let mut codes = vec![String::from("0"), color_code(cell.fg, false), color_code(cell.bg, true)];
+    let modifiers = [
+        (Modifier::BOLD, "1"), (Modifier::DIM, "2"), (Modifier::ITALIC, "3"), (Modifier::UNDERLINED, "4"),
+        (Modifier::SLOW_BLINK, "5"), (Modifier::RAPID_BLINK, "6"), (Modifier::REVERSED, "7"),
+        (Modifier::HIDDEN, "8"), (Modifier::CROSSED_OUT, "9"),
+    ];
+    for (modifier, code) in modifiers.iter() {
+        if cell.modifier.contains(*modifier) { codes.push(String::from(*code)); }
+    }
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+/// Text of `buf`, one line per row. With `color` the styles are set with ANSI escape codes,
+/// without the trailing spaces of every line are removed.
+pub fn to_ansi(buf: &Buffer, color: bool) -> String {
+    let mut out = String::new();
+    for y in buf.area.top()..buf.area.bottom() {
+        let mut line = String::new();
+        let mut style: Option<String> = None;
+        for x in buf.area.left()..buf.area.right() {
+            let cell = buf.get(x, y);
+            if color {
+                let s = sgr(cell);
+                if style.as_ref() != Some(&s) {
+                    line.push_str(&s);
+                    style = Some(s);
+                }
+            }
+            line.push_str(&cell.symbol);
+        }
+        if color {
+            line.push_str("\x1b[0m");
+        } else {
+            line.truncate(line.trim_end().len());
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+#[test]
+fn test_to_ansi() {
+    use tui::{layout::Rect, style::Style};
+    let mut buf = Buffer::empty(Rect::new(0, 0, 4, 2));
+    buf.set_string(0, 0, "ab", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD));
+    buf.set_string(0, 1, "c", Style::default().bg(Color::Rgb(1, 2, 3)));
+    assert_eq!(to_ansi(&buf, false), "ab\nc\n");
+    assert_eq!(to_ansi(&buf, true), "\x1b[0;31;49;1mab\x1b[0;39;49m  \x1b[0m\n\x1b[0;39;48;2;1;2;3mc\x1b[0;39;49m   \x1b[0m\n");
+}
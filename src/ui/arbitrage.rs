@@ -0,0 +1,50 @@
+///! Widget `ArbitrageTable`
+use crate::arbitrage::Loop;
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Widget},
+    layout::{Rect},
+    text::{Span, Spans},
+    buffer::{Buffer}
+};
+
+/// Widget ArbitrageTable
+///
+/// Shows the triangular arbitrage loops with the highest profit, one per line.
+pub struct ArbitrageTable<'a> {
+    loops: &'a [Loop],
+    fee_pct: f64,
+}
+
+impl<'a> ArbitrageTable<'a> {
+    pub fn new(loops: &'a [Loop], fee_pct: f64) -> ArbitrageTable<'a> {
+        ArbitrageTable { loops: loops, fee_pct: fee_pct }
+    }
+    /// Render a single loop
+    fn render_loop(i: usize, l: &Loop) -> Spans<'a> {
+        let style = if l.net > 0.0 { Style::default().fg(Color::Green) }
+                    else if l.gross > 0.0 { Style::default().fg(Color::Yellow) }
+                    else { Style::default() };
+        let route = format!("{} > {} > {} > {}", l.assets[0], l.assets[1], l.assets[2], l.assets[0]);
+        let markets = format!("{} {} {}", l.markets[0], l.markets[1], l.markets[2]);
+        Spans::from(vec![
+            Span::styled(format!("{:>4} ", i+1), Style::default().fg(Color::Gray)),
+            Span::styled(format!("{:<36} ", route), Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{:<36} ", markets)),
+            Span::styled(format!("{:>9.3} ", l.gross), style),
+            Span::styled(format!("{:>9.3}", l.net), style),
+        ])
+    }
+}
+
+impl<'a> Widget for ArbitrageTable<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let bold = Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC);
+        let header = format!("{:>4} {:<36} {:<36} {:>9} {:>9}", "#", "Loop", "Markets", "Gross %",
+                             format!("Net {}%", self.fee_pct));
+        buf.set_spans(0, 0, &Spans::from(vec![Span::styled(header, bold)]), area.width);
+        for (i, l) in self.loops.iter().take(area.height.saturating_sub(1) as usize).enumerate() {
+            buf.set_spans(0, 1+i as u16, &ArbitrageTable::render_loop(i, l), area.width);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+/// The currency converter popup
+use crate::utils::*;
+use crate::rates::Rates;
+use crate::ui::nice::Nice;
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Block, Borders, Clear, Paragraph},
+    layout::{Rect},
+    text::{Span, Spans},
+    backend::Backend,
+    terminal::Frame,
+};
+use inlinable_string::inline_string::INLINE_STRING_CAPACITY;
+
+/// The fields of the `Converter`, in Tab order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Amount,
+    From,
+    To,
+}
+
+/// State of the converter popup
+pub struct Converter {
+    pub amount: String,
+    pub from: String,
+    pub to: String,
+    pub field: Field,       // field that has the focus
+}
+
+impl Converter {
+    /// New `Converter` for 1 BTC in USDT
+    pub fn new() -> Self {
+        Converter { amount: String::from("1"), from: String::from("BTC"), to: String::from("USDT"), field: Field::Amount }
+    }
+    /// The text of the focused field
+    fn focused(self: &mut Self) -> &mut String {
+        match self.field {
+            Field::Amount => &mut self.amount,
+            Field::From => &mut self.from,
+            Field::To => &mut self.to,
+        }
+    }
+    /// Handle a typed character. Tab and Enter move the focus to the next field.
+    pub fn input(self: &mut Self, c: char) {
+        match c {
+            '\t' | '\n' => {
+                self.field = match self.field { Field::Amount => Field::From, Field::From => Field::To, Field::To => Field::Amount };
+            },
+            c if self.field == Field::Amount => { if c.is_ascii_digit() || c == '.' { self.amount.push(c); } },
+            c => {
+                // an asset has to fit in a `Symbol`
+                let field = self.focused();
+                if c.is_alphanumeric() && field.len()+c.len_utf8() <= INLINE_STRING_CAPACITY { field.push(c.to_ascii_uppercase()); }
+            }
+        }
+    }
+    /// Delete the last character of the focused field
+    pub fn backspace(self: &mut Self) {
+        self.focused().pop();
+    }
+    /// Pick the previous (`step=-1`) or next (`step=1`) of `assets` in the focused asset field
+    pub fn pick(self: &mut Self, assets: &[Symbol], step: i32) {
+        if self.field == Field::Amount || assets.is_empty() { return; }
+        let current = self.focused().clone();
+        let n = assets.len() as i32;
+        let i = match assets.iter().position(|a| **a == *current) {
+            Some(i) => (i as i32 + step).rem_euclid(n),
+            None => 0
+        };
+        *self.focused() = assets[i as usize].to_string();
+    }
+    /// Converted amount and route, if there is one
+    pub fn convert(self: &Self, rates: &Rates) -> Option<(f64, Vec<Symbol>)> {
+        let amount: f64 = self.amount.parse().ok()?;
+        let from = parse_symbol(&self.from).ok()?;
+        let to = parse_symbol(&self.to).ok()?;
+        let route = rates.route(&from, &to)?;
+        Some((rates.convert(amount, &from, &to)?, route))
+    }
+}
+
+/// Draw the converter popup centered in `area`
+pub fn draw_converter<B: Backend>(f: &mut Frame<B>, converter: &Converter, rates: &Rates, area: Rect) {
+    let width = area.width.min(60);
+    let height = 6.min(area.height);
+    let popup = Rect::new(area.x + (area.width-width)/2, area.y + (area.height-height)/2, width, height);
+    let field = |label: &'static str, text: &str, focused: bool| {
+        let style = if focused { Style::default().add_modifier(Modifier::REVERSED) } else { Style::default() };
+        vec![Span::styled(label, Style::default().fg(Color::Gray)), Span::styled(format!("{:<12}", text), style), Span::raw(" ")]
+    };
+    let mut line: Vec<Span> = Vec::new();
+    line.append(&mut field("Amount ", &converter.amount, converter.field == Field::Amount));
+    line.append(&mut field("From ", &converter.from, converter.field == Field::From));
+    line.append(&mut field("To ", &converter.to, converter.field == Field::To));
+    let (result, route) = match converter.convert(rates) {
+        Some((value, route)) => (
+            Span::styled(format!("{} {} = {} {}", converter.amount, converter.from, value.compact_str(), converter.to),
+                         Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(format!("via {}", route.iter().map(|a| a.to_string()).collect::<Vec<String>>().join(" > ")),
+                         Style::default().fg(Color::Gray))
+        ),
+        None => (Span::styled("No conversion", Style::default().fg(Color::Red)), Span::raw(""))
+    };
+    let text = vec![Spans::from(line), Spans::from(vec![]), Spans::from(vec![result]), Spans::from(vec![route])];
+    let p = Paragraph::new(text)
+        .block(Block::default().title("Converter (Tab: next field, Up/Down: pick asset, Esc: close)").borders(Borders::ALL))
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+    f.render_widget(Clear, popup);
+    f.render_widget(p, popup);
+}
@@ -0,0 +1,104 @@
+///! Widget `DepthChart`
+use crate::utils::*;
+use crate::book::OrderBook;
+use crate::ui::nice::{f64_nice_range, Nice};
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Axis, Chart, Widget, Block, Dataset, GraphType, Paragraph},
+    layout::{Rect},
+    text::{Span},
+    buffer::{Buffer},
+    symbols
+};
+
+/// Only levels within this fraction of the mid price are shown
+const DEPTH_CHART_RANGE: f64 = 0.01;
+
+/// Widget DepthChart
+///
+/// Shows the cumulative bid (green) and ask (red) sizes against price around the mid price.
+pub struct DepthChart<'a> {
+    book: &'a OrderBook,
+}
+
+impl<'a> DepthChart<'a> {
+    pub fn new(book: &'a OrderBook) -> DepthChart<'a> {
+        DepthChart { book: book }
+    }
+    /// Make the data points of a cumulative step line starting at the best level
+    fn steps(levels: &[Level], limit: f64, is_bid: bool) -> Vec<(f64, f64)> {
+        let mut data: Vec<(f64, f64)> = Vec::with_capacity(levels.len()*2);
+        let mut cum = 0.0;
+        for (px, qty) in levels.iter() {
+            let px = dec_to_f64(*px);
+            if (is_bid && px < limit) || (!is_bid && px > limit) { break; }
+            data.push((px, cum));
+            cum += dec_to_f64(*qty);
+            data.push((px, cum));
+        }
+        if let Some(&(_, cum)) = data.last() {
+            data.push((limit, cum));    // extend the line to the edge of the chart
+        }
+        data
+    }
+}
+
+impl<'a> Widget for DepthChart<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let (bid, ask) = match self.book.best() {
+            Some((bid, ask)) if self.book.is_synced() => (dec_to_f64(bid), dec_to_f64(ask)),
+            _ => {
+                Paragraph::new("Waiting for order book...")
+                .style(Style::default().fg(Color::Gray))
+                .block(Block::default().title(self.book.symbol.to_string()))
+                .render(area, buf);
+                return;
+            }
+        };
+        let mid = (bid+ask)/2.0;
+        let (p_min, p_max) = (mid*(1.0-DEPTH_CHART_RANGE), mid*(1.0+DEPTH_CHART_RANGE));
+        let bids = DepthChart::steps(&self.book.bids(usize::MAX), p_min, true);
+        let asks = DepthChart::steps(&self.book.asks(usize::MAX), p_max, false);
+        let q_max = bids.iter().chain(asks.iter()).map(|(_, q)| *q).fold(0.0, f64::max);
+        let (_, q_max) = f64_nice_range(0.0, q_max);
+        let datasets = vec![
+            Dataset::default()
+                .name("Bids")
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Green))
+                .data(bids.as_slice()),
+            Dataset::default()
+                .name("Asks")
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(Color::Red))
+                .data(asks.as_slice()),
+        ];
+        let x_labels = vec![
+            Span::styled(p_min.compact_str(), Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(mid.compact_str()),
+            Span::styled(p_max.compact_str(), Style::default().add_modifier(Modifier::BOLD)),
+        ];
+        let y_labels = vec![
+            Span::styled(String::from("0"), Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw((q_max/2.0).compact_str()),
+            Span::styled(q_max.compact_str(), Style::default().add_modifier(Modifier::BOLD)),
+        ];
+        let title = match self.book.spread() {
+            Some((spread, bps)) => format!("{}  spread {} ({:.2}bps)", self.book.symbol, fmt_dec(spread), dec_to_f64(bps)),
+            None => self.book.symbol.to_string()
+        };
+        let chart = Chart::new(datasets)
+            .block(Block::default().title(title))
+            .x_axis(Axis::default()
+                .style(Style::default().fg(Color::White))
+                .bounds([p_min, p_max])
+                .labels(x_labels))
+            .y_axis(Axis::default()
+                .style(Style::default().fg(Color::White))
+                .bounds([0.0, q_max])
+                .labels(y_labels));
+        chart.render(area, buf);
+    }
+}
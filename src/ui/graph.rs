@@ -32,7 +32,7 @@ impl<'a> Graph<'a> {
 
 impl<'a> Widget for Graph<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        if self.klines.len() < 1 {
+        if self.klines.is_empty() {
             Paragraph::new("No data!")
             .style(Style::default().fg(Color::Red))
             .block(
@@ -0,0 +1,136 @@
+///! Widget `Heatmap`
+use crate::utils::*;
+use crate::ui::MarketState;
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Widget},
+    layout::{Rect},
+    text::{Span, Spans},
+    buffer::{Buffer}
+};
+use std::collections::HashMap;
+
+/// Maximum number of tiles
+const HEATMAP_TILES: usize = 80;
+
+/// 24h change in percent that gets the most intense color
+const HEATMAP_FULL_CHANGE: f64 = 10.0;
+
+/// A tile of the heatmap: base asset, total volume and 24h change of its main market
+struct Tile {
+    base: Symbol,
+    volume: f64,
+    change: f64,
+}
+
+/// Widget Heatmap
+///
+/// Treemap of the base assets, the area of a tile is proportional to the trading volume of the
+/// base over all its markets, the color shows the 24h change of its USDT market (or, if there is
+/// none, its most liquid market).
+pub struct Heatmap {
+    tiles: Vec<Tile>,
+}
+
+impl Heatmap {
+    pub fn new(infos: &[Info], markets: &HashMap<Symbol, MarketState>) -> Heatmap {
+        let mut tiles: Vec<Tile> = Vec::new();
+        let mut ix: HashMap<Symbol, usize> = HashMap::new();
+        for info in infos.iter() {   // infos are sorted by volume, so the first market of a base is its most liquid
+            let volume = dec_to_f64(info.volume);
+            if !volume.is_finite() || info.base == "USDT" { continue; }
+            let change = markets.get(&info.symbol).map(|m| m.change_24h()).unwrap_or(f64::NAN);
+            match ix.get(&info.base) {
+                Some(i) => {
+                    let tile = &mut tiles[*i];
+                    tile.volume += volume;
+                    if info.quote == "USDT" && change.is_finite() { tile.change = change; }
+                },
+                None => {
+                    ix.insert(info.base.clone(), tiles.len());
+                    tiles.push(Tile { base: info.base.clone(), volume: volume, change: change });
+                }
+            }
+        }
+        tiles.sort_by(|a, b| b.volume.partial_cmp(&a.volume).unwrap_or(std::cmp::Ordering::Equal));
+        tiles.truncate(HEATMAP_TILES);
+        Heatmap { tiles: tiles }
+    }
+    /// Background color for 24h change `change`
+    fn color(change: f64) -> Color {
+        if !change.is_finite() { return Color::DarkGray; }
+        let level = (40.0 + (change.abs()/HEATMAP_FULL_CHANGE).min(1.0)*160.0) as u8;
+        if change >= 0.0 { Color::Rgb(0, level, 0) } else { Color::Rgb(level, 0, 0) }
+    }
+    /// Draw a single tile, with label and change centered if there is enough space
+    fn render_tile(tile: &Tile, r: Rect, buf: &mut Buffer) {
+        if r.width == 0 || r.height == 0 { return; }
+        let style = Style::default().fg(Color::White).bg(Heatmap::color(tile.change));
+        for y in r.y..r.y+r.height {
+            for x in r.x..r.x+r.width {
+                buf.get_mut(x, y).set_symbol(" ").set_style(style);
+            }
+        }
+        // leave a one char gap at the right for separating the tiles
+        let width = r.width.saturating_sub(1);
+        let change = if tile.change.is_finite() { format!("{:+.2}%", tile.change) } else { String::from("-") };
+        let mut lines: Vec<String> = vec![tile.base.to_string()];
+        if r.height >= 2 { lines.push(change); }
+        let y0 = r.y + r.height.saturating_sub(lines.len() as u16)/2;
+        for (i, line) in lines.iter().enumerate() {
+            if line.len() as u16 > width { continue; }
+            let x = r.x + (width-line.len() as u16)/2;
+            let span = Span::styled(line.clone(), style.add_modifier(if i == 0 { Modifier::BOLD } else { Modifier::empty() }));
+            buf.set_spans(x, y0+i as u16, &Spans::from(vec![span]), width);
+        }
+    }
+}
+
+/// Lay out tiles with `weights` (sorted descending) in `area`.
+///
+/// The tiles are split into two groups of about equal weight, the area is split in the same
+/// proportion along its longer side, and both halves are laid out recursively.
+pub fn treemap(weights: &[f64], area: Rect, out: &mut Vec<Rect>) {
+    match weights.len() {
+        0 => {},
+        1 => out.push(area),
+        n => {
+            let total: f64 = weights.iter().sum();
+            let mut k = 1;
+            let mut sum = weights[0];
+            while k < n-1 && (sum+weights[k]) <= total/2.0 { sum += weights[k]; k += 1; }
+            let frac = if total > 0.0 { sum/total } else { k as f64/n as f64 };
+            // terminal cells are about twice as high as wide
+            let (a, b) = if area.width >= 2*area.height {
+                let w = (area.width as f64*frac).round() as u16;
+                (Rect::new(area.x, area.y, w, area.height), Rect::new(area.x+w, area.y, area.width-w, area.height))
+            } else {
+                let h = (area.height as f64*frac).round() as u16;
+                (Rect::new(area.x, area.y, area.width, h), Rect::new(area.x, area.y+h, area.width, area.height-h))
+            };
+            treemap(&weights[..k], a, out);
+            treemap(&weights[k..], b, out);
+        }
+    }
+}
+
+impl Widget for Heatmap {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let weights: Vec<f64> = self.tiles.iter().map(|t| t.volume).collect();
+        let mut rects = Vec::with_capacity(weights.len());
+        treemap(&weights, area, &mut rects);
+        for (tile, r) in self.tiles.iter().zip(rects.iter()) {
+            Heatmap::render_tile(tile, *r, buf);
+        }
+    }
+}
+
+#[test]
+fn test_treemap() {
+    let area = Rect::new(0, 0, 80, 20);
+    let mut rects = Vec::new();
+    treemap(&[4.0, 2.0, 1.0, 1.0], area, &mut rects);
+    assert_eq!(rects.len(), 4);
+    assert_eq!(rects[0], Rect::new(0, 0, 40, 20));
+    assert_eq!(rects.iter().map(|r| r.area()).sum::<u16>(), area.area());
+}
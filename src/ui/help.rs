@@ -13,9 +13,28 @@ pub fn help<'a>() -> Paragraph<'a> {
     , ("g",    "Show graph at current time scale")
     , ("0..9", "Show graph at time scale 0 to 9 (1m to 1d)")
     , ("s",    "Select symbol")
+    , ("e",    "Export klines (graph page) or prices (other pages) to a file")
+    , ("p",    "Show portfolio page")
+    , ("c",    "Open currency converter")
+    , ("T",    "Show triangular arbitrage page")
+    , ("H",    "Show market heatmap")
+    , ("m",    "Show volatility and correlation matrix")
+    , ("←→",   "Change interval (volatility and correlation page)")
+    , ("↑↓",   "Change lookback window (volatility and correlation page)")
+    , ("A",    "Show alerts page")
+    , ("n",    "New alert rule")
+    , ("Del",  "Delete selected alert rule (alerts page)")
+    , ("B",    "Toggle alert bell")
+    , ("o",    "Show order book of selected symbol")
+    , ("d",    "Show depth chart of selected symbol")
+    , ("r",    "Show trades of selected symbol")
     , ("Home", "Set cursor to top left symbol (select symbol page)")
     , ("%",    "Toggle percent/price display")
     , ("x",    "Toggle extended/reduced view (Table display)")
+    , ("S",    "Toggle sparklines (List and Table display)")
+    , ("b",    "Toggle bid/ask/spread (List and Table display)")
+    , ("Space", "Pause/resume replay")
+    , (".",    "Step to next message (paused replay)")
     , ("a",    "Display about page")
     , ("Esc",  "Go back to previous view")
     , ("q",    "Quit")
@@ -0,0 +1,46 @@
+/// The input popup
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Block, Borders, Clear, Paragraph},
+    layout::{Rect},
+    text::{Span, Spans},
+    backend::Backend,
+    terminal::Frame,
+};
+
+/// What the text typed into the `Input` popup is used for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InputPurpose {
+    Alert,      // a new alert rule
+    ExportKlines,   // file name for exporting the klines of the graph
+    ExportSnapshot, // file name for exporting the prices
+}
+
+/// A single line text input
+pub struct Input {
+    pub prompt: String,         // shown as title of the popup
+    pub text: String,           // text typed so far
+    pub purpose: InputPurpose,
+}
+
+impl Input {
+    /// New empty `Input`
+    pub fn new(prompt: &str, purpose: InputPurpose) -> Self {
+        Input { prompt: String::from(prompt), text: String::new(), purpose: purpose }
+    }
+}
+
+/// Draw the input popup centered in `area`
+pub fn draw_input<B: Backend>(f: &mut Frame<B>, input: &Input, area: Rect) {
+    let width = area.width.min(60);
+    let popup = Rect::new(area.x + (area.width-width)/2, area.y + area.height.saturating_sub(3)/2, width, 3.min(area.height));
+    let text = Spans::from(vec![
+        Span::raw(input.text.as_str()),
+        Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)),  // cursor
+    ]);
+    let p = Paragraph::new(text)
+        .block(Block::default().title(input.prompt.as_str()).borders(Borders::ALL))
+        .style(Style::default().fg(Color::White).bg(Color::Black));
+    f.render_widget(Clear, popup);
+    f.render_widget(p, popup);
+}
@@ -0,0 +1,80 @@
+///! Widget `OrderBookLadder`
+use crate::utils::*;
+use crate::book::OrderBook;
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Widget},
+    layout::{Rect},
+    text::{Span, Spans},
+    buffer::{Buffer}
+};
+use dec::Decimal64;
+
+/// Widget OrderBookLadder
+///
+/// Shows the best asks on top (best ask at the bottom), the spread in the middle and the best bids
+/// below (best bid at the top). Each level has a bar proportional to the cumulative size.
+pub struct OrderBookLadder<'a> {
+    book: &'a OrderBook,
+}
+
+impl<'a> OrderBookLadder<'a> {
+    pub fn new(book: &'a OrderBook) -> OrderBookLadder<'a> {
+        OrderBookLadder { book: book }
+    }
+    /// Compute cumulative sizes of `levels`
+    fn cumulate(levels: &[Level]) -> Vec<(Decimal64, Decimal64, Decimal64)> {
+        let mut cum = Decimal64::from(0);
+        levels.iter().map(|(px, qty)| { cum += *qty; (*px, *qty, cum) }).collect()
+    }
+    /// Render a single level
+    fn render_level(level: &(Decimal64, Decimal64, Decimal64), max_cum: f64, bar_width: usize, style: Style) -> Spans<'a> {
+        let (px, qty, cum) = level;
+        let n = if max_cum > 0.0 { ((dec_to_f64(*cum)/max_cum)*bar_width as f64).round() as usize } else { 0 };
+        Spans::from(vec![
+            Span::styled(format!("{:>14} ", fmt_dec(*px)), style.add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{:>14} ", fmt_dec(*qty))),
+            Span::styled(format!("{:>14} ", fmt_dec(*cum)), Style::default().fg(Color::Gray)),
+            Span::styled("█".repeat(n.min(bar_width)), style),
+        ])
+    }
+}
+
+impl<'a> Widget for OrderBookLadder<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height < 3 { return; }
+        let bold = Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC);
+        // header
+        let header = format!("{:>14} {:>14} {:>14} ", "Price", "Size", "Total");
+        buf.set_spans(0, 0, &Spans::from(vec![Span::styled(self.book.symbol.to_string(), bold)]), area.width);
+        buf.set_spans(0, 1, &Spans::from(vec![Span::styled(header, bold)]), area.width);
+        if !self.book.is_synced() {
+            buf.set_spans(0, 2, &Spans::from(vec![Span::styled("Waiting for snapshot...", Style::default().fg(Color::Gray))]), area.width);
+            return;
+        }
+        // levels, one line is for the spread
+        let n = ((area.height as usize).saturating_sub(3))/2;
+        let bids = OrderBookLadder::cumulate(&self.book.bids(n));
+        let asks = OrderBookLadder::cumulate(&self.book.asks(n));
+        let max_cum = bids.last().map(|l| dec_to_f64(l.2)).unwrap_or(0.0)
+                  .max(asks.last().map(|l| dec_to_f64(l.2)).unwrap_or(0.0));
+        let bar_width = (area.width as usize).saturating_sub(45);
+        let mut y = 2 + (n-asks.len()) as u16;
+        for level in asks.iter().rev() {
+            let spans = OrderBookLadder::render_level(level, max_cum, bar_width, Style::default().fg(Color::Red));
+            buf.set_spans(0, y, &spans, area.width);
+            y += 1;
+        }
+        let spread = match self.book.spread() {
+            Some((spread, bps)) => format!("{:>14} {:>14} ", fmt_dec(spread), format!("{:.2}bps", dec_to_f64(bps))),
+            None => String::from("-")
+        };
+        buf.set_spans(0, y, &Spans::from(vec![Span::styled(spread, Style::default().add_modifier(Modifier::ITALIC))]), area.width);
+        y += 1;
+        for level in bids.iter() {
+            let spans = OrderBookLadder::render_level(level, max_cum, bar_width, Style::default().fg(Color::Green));
+            buf.set_spans(0, y, &spans, area.width);
+            y += 1;
+        }
+    }
+}
@@ -0,0 +1,67 @@
+///! Widget `PortfolioTable`
+use crate::utils::*;
+use crate::portfolio::Valuation;
+use crate::ui::nice::Nice;
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Widget},
+    layout::{Rect},
+    text::{Span, Spans},
+    buffer::{Buffer}
+};
+
+/// Widget PortfolioTable
+///
+/// Shows quantity, price, value, cost, P&L and weight of each holding and the total.
+pub struct PortfolioTable<'a> {
+    quote: &'a Symbol,
+    valuations: &'a Vec<Valuation>,
+    total: &'a Valuation,
+}
+
+/// Format a f64 with `decimals` decimals, `-` for NAN
+fn fmt_f64(x: f64, decimals: usize) -> String {
+    if x.is_finite() { format!("{:.*}", decimals, x) } else { String::from("-") }
+}
+
+impl<'a> PortfolioTable<'a> {
+    pub fn new(quote: &'a Symbol, valuations: &'a Vec<Valuation>, total: &'a Valuation) -> PortfolioTable<'a> {
+        PortfolioTable { quote: quote, valuations: valuations, total: total }
+    }
+    /// Render a single valuation
+    fn render_valuation(v: &Valuation, style: Style) -> Spans<'a> {
+        let pnl_style = if v.pnl > 0.0 { Style::default().fg(Color::Green) }
+                        else if v.pnl < 0.0 { Style::default().fg(Color::Red) }
+                        else { Style::default() };
+        let px = if v.px.is_finite() { v.px.compact_str() } else { String::from("-") };
+        Spans::from(vec![
+            Span::styled(format!("{:<8} ", v.asset), style.add_modifier(Modifier::BOLD)),
+            Span::styled(format!("{:>14} ", if v.qty.is_finite() { v.qty.compact_str() } else { String::new() }), style),
+            Span::styled(format!("{:>14} ", if v.qty.is_finite() { px } else { String::new() }), style),
+            Span::styled(format!("{:>14} ", fmt_f64(v.value, 2)), style),
+            Span::styled(format!("{:>14} ", fmt_f64(v.cost, 2)), style),
+            Span::styled(format!("{:>14} ", fmt_f64(v.pnl, 2)), pnl_style),
+            Span::styled(format!("{:>8} ", if v.pnl_pct.is_finite() { format!("{:+.2}", v.pnl_pct) } else { String::from("-") }), pnl_style),
+            Span::styled(format!("{:>7}", fmt_f64(v.weight, 1)), style),
+        ])
+    }
+}
+
+impl<'a> Widget for PortfolioTable<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let bold = Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC);
+        let header = format!("{:<8} {:>14} {:>14} {:>14} {:>14} {:>14} {:>8} {:>7}",
+                             "Asset", "Quantity", format!("Price {}", self.quote), format!("Value {}", self.quote),
+                             "Cost", "P&L", "P&L %", "Weight");
+        buf.set_spans(0, 0, &Spans::from(vec![Span::styled(header, bold)]), area.width);
+        let mut y: u16 = 1;
+        for v in self.valuations.iter() {
+            if y+2 >= area.height { break; }
+            buf.set_spans(0, y, &PortfolioTable::render_valuation(v, Style::default()), area.width);
+            y += 1;
+        }
+        if y+1 < area.height {
+            buf.set_spans(0, y+1, &PortfolioTable::render_valuation(self.total, Style::default().add_modifier(Modifier::ITALIC)), area.width);
+        }
+    }
+}
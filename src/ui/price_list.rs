@@ -1,7 +1,7 @@
 
 ///! Widget `PriceList`
 use crate::utils::*;
-use crate::ui::MarketState;
+use crate::ui::{MarketState, SPARKLINE_WIDTH};
 use tui::{
     style::{Style, Color, Modifier},
     widgets::{Widget},
@@ -15,12 +15,16 @@ use std::collections::HashMap;
 pub struct PriceList<'a> {
     infos: &'a Vec<Info>,                       // sorted list of `Info`
     markets: &'a HashMap<Symbol, MarketState>,  // map symbol to `MarketState`
-    show_percent: bool                          // flag indicating whether % change should be shown
+    show_percent: bool,                         // flag indicating whether % change should be shown
+    show_sparkline: bool,                       // flag indicating whether the sparkline should be shown
+    show_book: bool,                            // flag indicating whether bid, ask and spread should be shown
 }
 
 impl<'a> PriceList<'a> {
-    pub fn new(infos: &'a Vec<Info>, markets: &'a HashMap<Symbol, MarketState>, show_percent: bool) -> PriceList<'a> {
-        PriceList {infos: infos, markets: markets, show_percent: show_percent }
+    pub fn new(infos: &'a Vec<Info>, markets: &'a HashMap<Symbol, MarketState>, 
+               show_percent: bool, show_sparkline: bool, show_book: bool) -> PriceList<'a> {
+        PriceList {infos: infos, markets: markets, show_percent: show_percent, show_sparkline: show_sparkline,
+                   show_book: show_book }
     }
     fn render_info(self: &Self, info: &Info, width: usize) -> Spans<'a> {
         let grey = Style::default().fg(Color::Gray);
@@ -30,15 +34,26 @@ impl<'a> PriceList<'a> {
         let symbol_span = Span::styled(format!("{} ",symbol), 
                                         Style::default().add_modifier(Modifier::BOLD)
                                                         .add_modifier(Modifier::ITALIC));
+        let mut spans = vec![symbol_span];
+        if self.show_sparkline {
+            let spark = mkt.map(|m| m.sparkline_string(SPARKLINE_WIDTH))
+                           .unwrap_or(format!("{:>width$}", "-", width=SPARKLINE_WIDTH));
+            spans.push(Span::styled(spark+" ", mkt.map(|m| m.style_sparkline()).unwrap_or(grey)));
+        }
         if self.show_percent {
             let percentage = mkt.map(|s| String::from(" ")+&s.percentage_string()).unwrap_or(String::from("-"));
-            let percentage_span = Span::styled(percentage, mkt.map(|m| m.style_percent()).unwrap_or(grey));
-            Spans::from(vec![symbol_span, percentage_span])
+            spans.push(Span::styled(percentage, mkt.map(|m| m.style_percent()).unwrap_or(grey)));
         } else {
-                let px = mkt.map(|s| s.price_string()).unwrap_or(String::from("-"));
-                let price_span = Span::styled(px, mkt.map(|m| m.style()).unwrap_or(grey));
-                Spans::from(vec![symbol_span, price_span])
+            let px = mkt.map(|s| s.price_string()).unwrap_or(String::from("-"));
+            let px = if self.show_book { format!("{:<10}", px) } else { px };
+            spans.push(Span::styled(px, mkt.map(|m| m.style()).unwrap_or(grey)));
+        }
+        if self.show_book {
+            let (bid, ask) = mkt.map(|m| m.bid_ask_strings()).unwrap_or((String::from("-"), String::from("-")));
+            let spread = mkt.map(|m| m.spread_string()).unwrap_or(format!("{:>6}", "-"));
+            spans.push(Span::styled(format!(" {:>10} {:>10} {}", bid, ask, spread), grey));
         }
+        Spans::from(spans)
     }
     fn render_infos(self: &Self, infos: &'a [Info]) -> (usize, Vec<Spans>) {
         let width: usize = infos.iter().map(|i| i.short_symbol().len()).max().unwrap_or(0).max(8);
@@ -1,7 +1,9 @@
 
 ///! Widget `PriceList`
 use crate::utils::*;
-use crate::ui::MarketState;
+use crate::ui::{MarketState, SPARKLINE_WIDTH};
+use crate::ui::nice::Nice;
+use crate::rates::Rates;
 use tui::{
     style::{Style, Color, Modifier},
     widgets::{Widget},
@@ -17,16 +19,21 @@ pub struct PriceTable<'a> {
     markets: &'a HashMap<Symbol, MarketState>,  // map symbol to `MarketState`
     show_percent: bool,                         // flag indicating whether % change should be shown
     extended: bool,                             // flag indicating extended view vs. reduced
+    show_sparkline: bool,                       // flag indicating whether sparklines should be shown
+    show_book: bool,                            // flag indicating whether bid, ask and spread should be shown
     quotes: Vec<Symbol>,
     bases: Vec<Symbol>,
+    rates: Rates,                               // cross rates for cells without market
 }
 
 impl<'a> PriceTable<'a> {
     pub fn new(infos: &'a Vec<Info>, markets: &'a HashMap<Symbol, MarketState>, 
-               show_percent: bool, extended: bool) -> PriceTable<'a> {
+               show_percent: bool, extended: bool, show_sparkline: bool, show_book: bool) -> PriceTable<'a> {
         let (bases ,quotes) = sort_base_quote(&infos);
+        let rates = Rates::new(infos, markets);
         PriceTable {infos: infos, markets: markets, show_percent: show_percent, extended: extended, 
-                    quotes: quotes, bases: bases }
+                    show_sparkline: show_sparkline, show_book: show_book, quotes: quotes, bases: bases,
+                    rates: rates }
     }
     fn render_info(self: &Self, info: &Info, width: usize) -> Spans<'a> {
         let grey = Style::default().fg(Color::Gray);
@@ -54,7 +61,12 @@ impl<'a> PriceTable<'a> {
 
 impl<'a> Widget for PriceTable<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let col_width = if self.show_percent {6} else {8};
+        let base_width = if self.show_percent {6} else {8};
+        // the sparkline goes in front of the price or percentage
+        let spark_width: u16 = if self.show_sparkline {SPARKLINE_WIDTH as u16 + 1} else {0};
+        // bid, ask and spread go behind the price or percentage
+        let book_width: u16 = if self.show_book {8+1+8+1+6} else {0};
+        let col_width = base_width + spark_width + if self.show_book {book_width+1} else {0};
         let mut x: u16 = 0;
         let height = area.height as usize;
         let mut counter: usize = 0;
@@ -68,7 +80,7 @@ impl<'a> Widget for PriceTable<'a> {
                 let span = Span::styled(String::from(&**base), Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC));
                 buf.set_spans(x, y as u16+1, &Spans::from(vec![span]), base.len() as u16);
             }
-            x += col_width + 2;
+            x += base_width + 2;
             let quotes = if self.extended {
                 vec!["USDT", "BTC", "EUR", "GBP", "BNB", "ETH"]     // extended view
             } else {
@@ -84,6 +96,18 @@ impl<'a> Widget for PriceTable<'a> {
                     let mut symbol = base.clone();
                     symbol.push_str(quote).unwrap(); // this should really be ok! If not, something weird is happening
                     if let Some(mkt) = self.markets.get(&symbol) {
+                        if self.show_sparkline && x+spark_width < area.width {
+                            let spark_span = Span::styled(mkt.sparkline_string(SPARKLINE_WIDTH), mkt.style_sparkline());
+                            let spans = Spans::from(vec![spark_span]);
+                            buf.set_spans(x, y as u16+1, &spans, SPARKLINE_WIDTH as u16);
+                        }
+                        let x = x + spark_width;
+                        if self.show_book && x+base_width+1+book_width < area.width {
+                            let (bid, ask) = mkt.bid_ask_strings();
+                            let book = format!("{:>8} {:>8} {}", bid, ask, mkt.spread_string());
+                            let spans = Spans::from(vec![Span::styled(book, Style::default().fg(Color::Gray))]);
+                            buf.set_spans(x+base_width+1, y as u16+1, &spans, book_width);
+                        }
                         if self.show_percent {
                             let percentage = mkt.percentage_string();
                             let perc_len = percentage.len() as u16;
@@ -101,6 +125,22 @@ impl<'a> Widget for PriceTable<'a> {
                                 buf.set_spans(x, y as u16+1, &spans, price_len);
                             }
                         }
+                    } else if !self.show_percent && **base != **quote {
+                        // no market, show the derived cross rate in italics
+                        if let Some(px) = self.rates.rate(base, &Symbol::from(*quote)) {
+                            let price = if px >= 1.0 {
+                                let digits = px.log10().floor() as usize + 1;
+                                format!("{:.*}", 7usize.saturating_sub(digits), px)
+                            } else {
+                                px.compact_str()
+                            };
+                            let price_len = (price.len() as u16).min(base_width);
+                            let x = x + spark_width;
+                            if x+price_len < area.width {
+                                let price_span = Span::styled(price, Style::default().fg(Color::Gray).add_modifier(Modifier::ITALIC));
+                                buf.set_spans(x, y as u16+1, &Spans::from(vec![price_span]), price_len);
+                            }
+                        }
                     }
                 }
                 x += col_width+2;
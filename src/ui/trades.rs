@@ -0,0 +1,63 @@
+///! Widget `TradesTape`
+use crate::utils::*;
+use crate::trades::Tape;
+use tui::{
+    style::{Style, Color, Modifier},
+    widgets::{Widget},
+    layout::{Rect},
+    text::{Span, Spans},
+    buffer::{Buffer}
+};
+use chrono::{Local, TimeZone};
+
+/// Widget TradesTape
+///
+/// Shows the most recent trades (time, price, quantity), buys in green and sells in red,
+/// and the buy/sell volume imbalance since the tape was started.
+pub struct TradesTape<'a> {
+    tape: &'a Tape,
+}
+
+impl<'a> TradesTape<'a> {
+    pub fn new(tape: &'a Tape) -> TradesTape<'a> {
+        TradesTape { tape: tape }
+    }
+    /// Render the imbalance line: buy and sell volume and a bar centered at 0
+    fn render_imbalance(self: &Self, width: usize) -> Spans<'a> {
+        let imbalance = self.tape.imbalance();
+        let text = format!("Buy {} Sell {} Imbalance {} ",
+                           fmt_dec(self.tape.buy_volume), fmt_dec(self.tape.sell_volume),
+                           if imbalance.is_nan() { String::from("-") } else { format!("{:+.1}%", imbalance*100.0) });
+        let half = width.saturating_sub(text.len()+2)/2;
+        let n = if imbalance.is_nan() { 0 } else { (imbalance.abs()*half as f64).round() as usize };
+        let (left, right) = if imbalance > 0.0 { (0, n) } else { (n, 0) };
+        Spans::from(vec![
+            Span::styled(text, Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(" ".repeat(half-left)),
+            Span::styled("█".repeat(left), Style::default().fg(Color::Red)),
+            Span::raw("|"),
+            Span::styled("█".repeat(right), Style::default().fg(Color::Green)),
+        ])
+    }
+}
+
+impl<'a> Widget for TradesTape<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        if area.height < 3 { return; }
+        let bold = Style::default().add_modifier(Modifier::BOLD | Modifier::ITALIC);
+        buf.set_spans(0, 0, &self.render_imbalance(area.width as usize), area.width);
+        let header = format!("{:<12} {:>14} {:>14}", "Time", "Price", "Quantity");
+        buf.set_spans(0, 1, &Spans::from(vec![Span::styled(header, bold)]), area.width);
+        for (y, trade) in self.tape.trades(area.height as usize-2).enumerate() {
+            let t = Local.timestamp_millis_opt(trade.ts as i64).single()
+                         .map(|t| t.format("%H:%M:%S%.3f").to_string()).unwrap_or(String::from("-"));
+            let style = if trade.is_buy { Style::default().fg(Color::Green) } else { Style::default().fg(Color::Red) };
+            let spans = Spans::from(vec![
+                Span::styled(format!("{:<12} ", t), Style::default().fg(Color::Gray)),
+                Span::styled(format!("{:>14} ", fmt_dec(trade.px)), style.add_modifier(Modifier::BOLD)),
+                Span::styled(format!("{:>14}", fmt_dec(trade.qty)), style),
+            ]);
+            buf.set_spans(0, y as u16+2, &spans, area.width);
+        }
+    }
+}
@@ -13,19 +13,19 @@ use inlinable_string::{InlineString};
 /// Parse a String into a `Decimal64`, chop off superfluous zeros
 // todo: Make this return Result
 pub fn parse_dec(s: &String) -> Decimal64 {
-    if let Some(_) = s.find(".") {
-        s.trim_end_matches("0").parse().expect("parse_dec: Couldn't parse!")
-    } else {
-        s.parse().expect("parse_dec: Couldn't parse!")
-    }
+    try_parse_dec(s).expect("parse_dec: Couldn't parse!")
+}
+
+/// `parse_dec` for untrusted input, an error instead of a panic
+pub fn try_parse_dec(s: &str) -> Result<Decimal64, String> {
+    let trimmed = if s.contains('.') { s.trim_end_matches('0') } else { s };
+    trimmed.parse().map_err(|_| format!("Bad number '{}'", s))
 }
 
 /// Nicely format a `Decimal64`
 // todo: move this into `Nice`
 pub fn fmt_dec(d: Decimal64) -> String {
-    if d.is_infinite() {
-        String::from("-")
-    } else if d.is_nan() || d.is_signaling_nan() {
+    if d.is_infinite() || d.is_nan() || d.is_signaling_nan() {
         String::from("-")
     } else {
         let mut u = d.coefficient();
@@ -44,9 +44,27 @@ pub fn fmt_dec(d: Decimal64) -> String {
     }
 }
 
+/// Convert a `Decimal64` into a `f64`, e.g. for charts. NAN if the conversion fails.
+pub fn dec_to_f64(d: Decimal64) -> f64 {
+    format!("{}", d).parse().unwrap_or(f64::NAN)
+}
+
 /// String type for symbol
 pub type Symbol = InlineString;
 
+/// `Symbol` of `s`, an error if `s` exceeds its capacity
+pub fn parse_symbol(s: &str) -> Result<Symbol, String> {
+    let mut symbol = Symbol::new();
+    symbol.push_str(s).map_err(|_| format!("Bad symbol '{}'", s))?;
+    Ok(symbol)
+}
+
+#[test]
+fn test_parse_symbol() {
+    assert_eq!(parse_symbol("BTCUSDT"), Ok(InlineString::from("BTCUSDT")));
+    assert!(parse_symbol(&"X".repeat(31)).is_err());
+}
+
 /// `Info` contains symbol, base, quote and precision
 #[derive(Debug, Clone)]
 pub struct Info {
@@ -119,7 +137,7 @@ struct Ticker {
 }
 
 /// Get all traded binance symbols
-pub fn get_markets<'a>() -> Result<HashMap<Symbol, Market>, Box<dyn std::error::Error>> {
+pub fn get_markets() -> Result<HashMap<Symbol, Market>, Box<dyn std::error::Error>> {
     let mut writer = Vec::with_capacity(1500000);   // 24hr size is <1MB usually
     if !request::get("https://api.binance.com/api/v3/ticker/24hr", &mut writer)?.status_code().is_success() {
         return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Req api/v3/ticker/24hr failed")));
@@ -167,7 +185,7 @@ pub async fn get_infos() -> Result<Vec<Info>, String> {
 #[tokio::test]
 async fn test_get_infos() -> Result<(), Box<dyn std::error::Error>> {
     let infos = get_infos().await?;
-    assert!(infos.len()>0);
+    assert!(!infos.is_empty());
     Ok(())
 }
 
@@ -179,7 +197,7 @@ pub fn sort_infos(mut infos: Vec<Info>) -> Vec<Info> {
  
 /// Generate a [`Symbol`]->[`Info`] [`HashMap`] from a `Vec<Symbol>`
 pub fn infos_to_lookup(infos: &Vec<Info>) -> HashMap<Symbol, Info> {
-    infos.iter().map(|item| (item.symbol.clone(), item.clone())).into_iter().collect()
+    infos.iter().map(|item| (item.symbol.clone(), item.clone())).collect()
 }
 
 /// Extract [`Vec`] of base strings and quote strings from [`Vec`] of [`Info`], sort by volume
@@ -189,20 +207,21 @@ pub fn sort_base_quote(infos: &Vec<Info>) -> (Vec<Symbol>, Vec<Symbol>) {
     for info in infos.iter() {
         if info.base == "USDT" { continue; }
         let vol = bases.entry(info.base.clone()).or_insert(Decimal64::from(0));
-        *vol = *vol+info.volume;
+        *vol += info.volume;
         let vol = quotes.entry(info.quote.clone()).or_insert(Decimal64::from(0));
-        *vol = *vol+info.volume;
+        *vol += info.volume;
     }
     let mut bases: Vec<(Symbol, &Decimal64)> = bases.iter().map(|(k,v)| (k.clone(),v)).collect();
     bases.sort_by(|a,b| b.1.partial_cmp(a.1).unwrap());
-    let bases: Vec<Symbol> = bases.iter().map(|(k,_)| InlineString::from((*k).clone())).collect();
+    let bases: Vec<Symbol> = bases.iter().map(|(k,_)| (*k).clone()).collect();
     let mut quotes: Vec<(Symbol, &Decimal64)> = quotes.iter().map(|(k,v)| (k.clone(),v)).collect();
     quotes.sort_by(|a,b| b.1.partial_cmp(a.1).unwrap());
-    let quotes: Vec<Symbol> = quotes.iter().map(|(k,_)| InlineString::from((*k).clone())).collect();
+    let quotes: Vec<Symbol> = quotes.iter().map(|(k,_)| (*k).clone()).collect();
     (bases, quotes)
 }
 
 /// A single ohlcv bar 
+#[derive(Debug, Clone, PartialEq)]
 pub struct Bar {
     pub t: u64, // open time
     pub o: f32,
@@ -215,7 +234,7 @@ pub struct Bar {
 /// Kline/Candlestick chart intervals.
 /// 
 /// See: https://binance-docs.github.io/apidocs/spot/en/#kline-candlestick-streams
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Interval {
     I1m, I3m, I5m, I15m, I30m, I1h, I2h, I4h, I6h, I8h, I12h, I1d, I3d, I1w, I1M
 }
@@ -282,6 +301,20 @@ impl Interval {
             Interval::I1M  => "1M",
         }
     }
+    /// All intervals, shortest first
+    pub const ALL: [Interval; 15] = [
+        Interval::I1m, Interval::I3m, Interval::I5m, Interval::I15m, Interval::I30m, Interval::I1h, Interval::I2h,
+        Interval::I4h, Interval::I6h, Interval::I8h, Interval::I12h, Interval::I1d, Interval::I3d, Interval::I1w, Interval::I1M
+    ];
+}
+
+impl std::str::FromStr for Interval {
+    type Err = String;
+    /// Parse the Binance notation, e.g. `15m`, `4h`, `1M`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Interval::ALL.iter().find(|i| i.str() == s).cloned()
+            .ok_or_else(|| format!("Unknown interval '{}', expected one of 1m 3m 5m 15m 30m 1h 2h 4h 6h 8h 12h 1d 3d 1w 1M", s))
+    }
 }
 
 /// Binance encodes a bar as a vector of various things, here are their types
@@ -306,10 +339,50 @@ fn parse_bar(bbar: &BinanceBar) -> Result<Bar, Box<dyn std::error::Error>> {
 ///  
 /// See: https://binance-docs.github.io/apidocs/spot/en/#kline-candlestick-data
 pub async fn get_klines(symbol: &Symbol, interval: &Interval) -> Result<Vec<Bar>, Box<dyn std::error::Error>> {
-    let uri = format!("https://api.binance.com/api/v3/klines?symbol={}&interval={}&limit=1000", symbol, interval);
+    get_klines_since(symbol, interval, None).await
+}
+
+/// Kline/candlestick bars for a symbol, the 1000 bars after `start` (millis) or the last 1000 bars
+pub async fn get_klines_since(symbol: &Symbol, interval: &Interval, start: Option<u64>) -> Result<Vec<Bar>, Box<dyn std::error::Error>> {
+    get_klines_between(symbol, interval, start, None).await
+}
+
+/// The request was rejected because of too many requests, retry after the given seconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimited(pub u64);
+
+impl std::fmt::Display for RateLimited {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rate limit exceeded, retry after {}s", self.0)
+    }
+}
+
+impl std::error::Error for RateLimited {}
+
+/// Kline/candlestick bars for a symbol, at most 1000 bars with open time between `start` and `end` (millis).
+///
+/// Fails with `RateLimited` when Binance asks to back off (HTTP 429). A ban of the IP (HTTP 418) is a plain error.
+pub async fn get_klines_between(symbol: &Symbol, interval: &Interval, start: Option<u64>, end: Option<u64>)
+        -> Result<Vec<Bar>, Box<dyn std::error::Error>> {
+    let mut uri = format!("https://api.binance.com/api/v3/klines?symbol={}&interval={}&limit=1000", symbol, interval);
+    if let Some(start) = start {
+        uri.push_str(&format!("&startTime={}", start));
+    }
+    if let Some(end) = end {
+        uri.push_str(&format!("&endTime={}", end));
+    }
     let mut writer = Vec::with_capacity(200000);   // klines size is <100kB usually
-    if !request::get(uri, &mut writer)?.status_code().is_success() {
-        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::Other, "Req api/v3/ticker/24hr failed")));
+    let res = request::get(uri, &mut writer)?;
+    let status = res.status_code();
+    if u16::from(status) == 429 || u16::from(status) == 418 {
+        let retry_after = res.headers().get("Retry-After").and_then(|s| s.parse().ok()).unwrap_or(60);
+        if u16::from(status) == 418 {
+            return Err(format!("Req api/v3/klines failed: IP banned by Binance, retry after {}s", retry_after).into());
+        }
+        return Err(Box::new(RateLimited(retry_after)));
+    }
+    if !status.is_success() {
+        return Err(format!("Req api/v3/klines failed: {}", status).into());
     }
     let cow = String::from_utf8_lossy(&writer);
     let bars: Vec<BinanceBar> = serde_json::from_str(cow.deref())?;
@@ -321,6 +394,47 @@ pub async fn get_klines(symbol: &Symbol, interval: &Interval) -> Result<Vec<Bar>
     Ok(out)
 }
 
+/// Pause between the requests of `get_klines_range`
+const KLINES_PAGE_PAUSE_MILLIS: u64 = 200;
+
+/// Maximum number of retries of a page of `get_klines_range` after hitting the rate limit
+const KLINES_MAX_RETRIES: u32 = 3;
+
+/// Kline/candlestick bars for a symbol with open time between `start` and `end` (millis).
+///
+/// Pages through the history 1000 bars at a time. When the rate limit is hit the download waits
+/// as long as Binance asks for, at most `KLINES_MAX_RETRIES` times per page.
+pub async fn get_klines_range(symbol: &Symbol, interval: &Interval, start: u64, end: u64)
+        -> Result<Vec<Bar>, Box<dyn std::error::Error>> {
+    let mut out: Vec<Bar> = Vec::new();
+    let mut next = start;
+    let mut retries = 0;
+    while next <= end {
+        let bars = match get_klines_between(symbol, interval, Some(next), Some(end)).await {
+            Ok(bars) => bars,
+            Err(e) => match e.downcast_ref::<RateLimited>() {
+                Some(RateLimited(secs)) if retries < KLINES_MAX_RETRIES => {
+                    retries += 1;
+                    tokio::time::sleep(std::time::Duration::from_secs(*secs)).await;
+                    continue;
+                },
+                _ => return Err(e)
+            }
+        };
+        retries = 0;
+        let last = match bars.last() {
+            Some(bar) => bar.t,
+            None => break
+        };
+        let full = bars.len() == 1000;
+        out.extend(bars);
+        if !full { break; }
+        next = last+1;
+        tokio::time::sleep(std::time::Duration::from_millis(KLINES_PAGE_PAUSE_MILLIS)).await;
+    }
+    Ok(out)
+}
+
 /// A single update item from the markets websocket stream
 #[derive(Debug, Clone)]
 pub struct Update {
@@ -330,6 +444,25 @@ pub struct Update {
     pub px_24h: Decimal64, // price 24h ago
 }
 
+/// Test fixture: `Symbol` of `s`
+#[cfg(test)]
+pub fn mock_symbol(s: &str) -> Symbol {
+    parse_symbol(s).expect("mock_symbol: Bad symbol!")
+}
+
+/// Test fixture: `Info` of the market `base`+`quote` with trading `volume`
+#[cfg(test)]
+pub fn mock_info(base: &str, quote: &str, volume: &str) -> Info {
+    Info { symbol: mock_symbol(&format!("{}{}", base, quote)), base: mock_symbol(base), quote: mock_symbol(quote),
+           volume: parse_dec(&volume.to_string()) }
+}
+
+/// Test fixture: `Update` of `symbol` at `ts` with price `px`, `px_24h` 24h ago
+#[cfg(test)]
+pub fn mock_update(symbol: &str, ts: u64, px: &str, px_24h: &str) -> Update {
+    Update { symbol: mock_symbol(symbol), ts: ts, px: parse_dec(&px.to_string()), px_24h: parse_dec(&px_24h.to_string()) }
+}
+
 /// A single update item from the markets websocket stream FOR DESER PURPOSES
 #[derive(Debug, Clone, Deserialize)]
 struct BinanceUpdate {
@@ -350,14 +483,209 @@ pub fn parse_updates<'a>(s: &String, out: &'a mut Vec::<Update>) -> Result<&'a V
     let updates: Vec<BinanceUpdate> = serde_json::from_str(s.as_str())?;
     for update in updates.iter() {
         let ts = update.ts;
-        let symbol = InlineString::from(update.symbol.as_str());
-        let px_24h:Decimal64 = parse_dec(&update.px_24h);
-        let px:Decimal64 = parse_dec(&update.px);
+        let symbol = parse_symbol(&update.symbol)?;
+        let px_24h:Decimal64 = try_parse_dec(&update.px_24h)?;
+        let px:Decimal64 = try_parse_dec(&update.px)?;
         out.push(Update{symbol: symbol, ts: ts as u64, px: px, px_24h: px_24h});
     }
     Ok(out)
 }
 
+#[test]
+fn test_parse_updates() {
+    let mut out = Vec::new();
+    assert!(parse_updates(&String::from(r#"[{"E":1,"s":"BTCUSDT","x":"1.50","c":"2"}]"#), &mut out).is_ok());
+    assert_eq!(out[0].px_24h, parse_dec(&String::from("1.5")));
+    assert!(parse_updates(&format!(r#"[{{"E":1,"s":"{}","x":"1","c":"2"}}]"#, "X".repeat(31)), &mut out).is_err());
+    assert!(parse_updates(&String::from(r#"[{"E":1,"s":"BTCUSDT","x":"1","c":"two"}]"#), &mut out).is_err());
+}
+
+/// A price level (price, quantity) of the order book
+pub type Level = (Decimal64, Decimal64);
+
+/// helper function for parsing Binance's `[price, quantity]` levels
+fn parse_levels(levels: &[(String, String)]) -> Result<Vec<Level>, String> {
+    levels.iter().map(|(px, qty)| Ok((try_parse_dec(px)?, try_parse_dec(qty)?))).collect()
+}
+
+/// Order book snapshot as retrieved by API GET /api/v3/depth
+#[derive(Debug, Clone)]
+pub struct DepthSnapshot {
+    pub last_update_id: u64,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+/// Data returned by api/v3/depth, for deserialisation only
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceDepthSnapshot {
+    last_update_id: u64,
+    bids: Vec<(String, String)>,
+    asks: Vec<(String, String)>,
+}
+
+/// Order book depth snapshot for a symbol.
+///
+/// See: https://binance-docs.github.io/apidocs/spot/en/#order-book
+pub async fn get_depth(symbol: &Symbol, limit: u32) -> Result<DepthSnapshot, Box<dyn std::error::Error>> {
+    let uri = format!("https://api.binance.com/api/v3/depth?symbol={}&limit={}", symbol, limit);
+    let mut writer = Vec::with_capacity(200000);   // depth size is <100kB usually
+    if !request::get(uri, &mut writer)?.status_code().is_success() {
+        return Err("Req api/v3/depth failed".into());
+    }
+    let cow = String::from_utf8_lossy(&writer);
+    let snapshot: BinanceDepthSnapshot = serde_json::from_str(cow.deref())?;
+    Ok(DepthSnapshot {
+        last_update_id: snapshot.last_update_id,
+        bids: parse_levels(&snapshot.bids)?,
+        asks: parse_levels(&snapshot.asks)?,
+    })
+}
+
+/// A single update item from the diff depth websocket stream
+#[derive(Debug, Clone)]
+pub struct DepthUpdate {
+    pub symbol: Symbol,     // Exchange symbol
+    pub ts: u64,            // timestamp (millis)
+    pub first_id: u64,      // first update id in event (`U`)
+    pub last_id: u64,       // final update id in event (`u`)
+    pub bids: Vec<Level>,   // bids to be updated, quantity 0 means remove
+    pub asks: Vec<Level>,   // asks to be updated, quantity 0 means remove
+}
+
+/// A single update item from the diff depth websocket stream FOR DESER PURPOSES
+#[derive(Debug, Clone, Deserialize)]
+struct BinanceDepthUpdate {
+    #[serde(rename = "E")]
+    ts: u64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "U")]
+    first_id: u64,
+    #[serde(rename = "u")]
+    last_id: u64,
+    #[serde(rename = "b")]
+    bids: Vec<(String, String)>,
+    #[serde(rename = "a")]
+    asks: Vec<(String, String)>,
+}
+
+/// Parse a diff depth ws stream message
+///
+/// See: https://binance-docs.github.io/apidocs/spot/en/#diff-depth-stream
+pub fn parse_depth_update(s: &str) -> Result<DepthUpdate, Box<dyn std::error::Error>> {
+    let update: BinanceDepthUpdate = serde_json::from_str(s)?;
+    Ok(DepthUpdate {
+        symbol: parse_symbol(&update.symbol)?,
+        ts: update.ts,
+        first_id: update.first_id,
+        last_id: update.last_id,
+        bids: parse_levels(&update.bids)?,
+        asks: parse_levels(&update.asks)?,
+    })
+}
+
+/// A single aggregated trade from the aggTrade websocket stream
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub symbol: Symbol,     // Exchange symbol
+    pub ts: u64,            // trade time (millis)
+    pub px: Decimal64,      // price
+    pub qty: Decimal64,     // quantity
+    pub is_buy: bool,       // true if the buyer was the aggressor
+}
+
+/// A single aggregated trade from the aggTrade websocket stream FOR DESER PURPOSES
+#[derive(Debug, Clone, Deserialize)]
+struct BinanceTrade {
+    #[serde(rename = "T")]
+    ts: u64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "p")]
+    px: String,
+    #[serde(rename = "q")]
+    qty: String,
+    #[serde(rename = "m")]
+    buyer_is_maker: bool,
+}
+
+/// Parse an aggTrade ws stream message
+///
+/// See: https://binance-docs.github.io/apidocs/spot/en/#aggregate-trade-streams
+pub fn parse_trade(s: &str) -> Result<Trade, Box<dyn std::error::Error>> {
+    let trade: BinanceTrade = serde_json::from_str(s)?;
+    Ok(Trade {
+        symbol: parse_symbol(&trade.symbol)?,
+        ts: trade.ts,
+        px: try_parse_dec(&trade.px)?,
+        qty: try_parse_dec(&trade.qty)?,
+        is_buy: !trade.buyer_is_maker,
+    })
+}
+
+/// Best bid and ask of a symbol from the bookTicker websocket stream
+#[derive(Debug, Clone)]
+pub struct BookTicker {
+    pub symbol: Symbol,     // Exchange symbol
+    pub bid: Decimal64,     // best bid price
+    pub bid_qty: Decimal64, // best bid quantity
+    pub ask: Decimal64,     // best ask price
+    pub ask_qty: Decimal64, // best ask quantity
+}
+
+/// Best bid and ask from the bookTicker websocket stream FOR DESER PURPOSES
+#[derive(Debug, Clone, Deserialize)]
+struct BinanceBookTicker {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bid: String,
+    #[serde(rename = "B")]
+    bid_qty: String,
+    #[serde(rename = "a")]
+    ask: String,
+    #[serde(rename = "A")]
+    ask_qty: String,
+}
+
+/// Combined stream wrapper `{"stream": ..., "data": ...}` FOR DESER PURPOSES
+#[derive(Debug, Clone, Deserialize)]
+struct BinanceCombined<T> {
+    data: T
+}
+
+/// Parse a bookTicker ws stream message, either raw or wrapped by a combined stream
+///
+/// See: https://binance-docs.github.io/apidocs/spot/en/#individual-symbol-book-ticker-streams
+pub fn parse_book_ticker(s: &str) -> Result<BookTicker, Box<dyn std::error::Error>> {
+    let ticker: BinanceBookTicker = match serde_json::from_str::<BinanceCombined<BinanceBookTicker>>(s) {
+        Ok(combined) => combined.data,
+        Err(_) => serde_json::from_str(s)?
+    };
+    Ok(BookTicker {
+        symbol: parse_symbol(&ticker.symbol)?,
+        bid: try_parse_dec(&ticker.bid)?,
+        bid_qty: try_parse_dec(&ticker.bid_qty)?,
+        ask: try_parse_dec(&ticker.ask)?,
+        ask_qty: try_parse_dec(&ticker.ask_qty)?,
+    })
+}
+
+#[test]
+fn test_parse_book_ticker() -> Result<(), Box<dyn std::error::Error>> {
+    let raw = r#"{"u":400900217,"s":"BNBUSDT","b":"25.35190000","B":"31.21000000","a":"25.36520000","A":"40.66000000"}"#;
+    let combined = format!(r#"{{"stream":"bnbusdt@bookTicker","data":{}}}"#, raw);
+    for s in [String::from(raw), combined].iter() {
+        let ticker = parse_book_ticker(s)?;
+        assert_eq!(ticker.symbol, "BNBUSDT");
+        assert_eq!(ticker.bid, parse_dec(&String::from("25.3519")));
+        assert_eq!(ticker.ask, parse_dec(&String::from("25.3652")));
+    }
+    Ok(())
+}
+
 /// Get system timestamp in microseconds
 pub fn now_timestamp() -> u64 {
     let ts = SystemTime::now();